@@ -0,0 +1,368 @@
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use moka::future::Cache;
+use moka::policy::EvictionPolicy;
+use moka::Expiry;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+use tracing::{debug, instrument, warn};
+
+use crate::config::Config;
+use crate::error::ProxyError;
+
+/// Prefix of every personal access token issued by the website.
+pub const TOKEN_PREFIX: &str = "dxs_";
+
+/// Cloudflare REST API base URL.
+const CLOUDFLARE_API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+/// A successfully validated personal access token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatInfo {
+    pub pat_id: String,
+    pub tenant_id: String,
+}
+
+/// Cached outcome of a PAT lookup.
+///
+/// Valid and invalid tokens live in the same cache so that both count
+/// against the same size cap.
+#[derive(Debug, Clone)]
+enum CachedPat {
+    Valid(PatInfo),
+    Invalid,
+}
+
+/// Per-entry expiry: positive and negative results use different TTLs.
+struct PatExpiry {
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl Expiry<String, CachedPat> for PatExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &String,
+        value: &CachedPat,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        match value {
+            CachedPat::Valid(_) => Some(self.ttl),
+            CachedPat::Invalid => Some(self.negative_ttl),
+        }
+    }
+}
+
+/// Connection settings for the Cloudflare D1 database holding PATs.
+#[derive(Debug, Clone)]
+pub struct D1Config {
+    /// Cloudflare REST API base URL.
+    pub api_base: String,
+    pub account_id: String,
+    pub api_token: String,
+    pub database_id: String,
+}
+
+impl D1Config {
+    /// Build the D1 settings from the proxy configuration.
+    pub fn from_config(config: &Config) -> Result<Self, ProxyError> {
+        let require = |value: &Option<String>, flag: &str| {
+            value
+                .clone()
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| ProxyError::Config(format!("--{} is required", flag)))
+        };
+
+        Ok(Self {
+            api_base: CLOUDFLARE_API_BASE.to_string(),
+            account_id: require(&config.cloudflare_account_id, "cloudflare-account-id")?,
+            api_token: require(&config.cloudflare_api_token, "cloudflare-api-token")?,
+            database_id: require(&config.d1_database_id, "d1-database-id")?,
+        })
+    }
+
+    fn query_url(&self) -> String {
+        format!(
+            "{}/accounts/{}/d1/database/{}/query",
+            self.api_base.trim_end_matches('/'),
+            self.account_id,
+            self.database_id
+        )
+    }
+}
+
+/// Sizing and expiry of the PAT cache.
+#[derive(Debug, Clone, Copy)]
+pub struct PatCacheConfig {
+    /// TTL for tokens that validated successfully.
+    pub ttl: Duration,
+    /// TTL for tokens that were rejected.
+    pub negative_ttl: Duration,
+    /// Maximum number of cached entries, valid and invalid combined.
+    pub max_entries: u64,
+}
+
+impl PatCacheConfig {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.pat_cache_ttl_secs),
+            negative_ttl: Duration::from_secs(config.pat_negative_cache_ttl_secs),
+            max_entries: config.pat_cache_max_entries,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct D1Response {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<D1Message>,
+    #[serde(default)]
+    result: Vec<D1QueryResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct D1Message {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct D1QueryResult {
+    #[serde(default)]
+    results: Vec<PatRow>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatRow {
+    id: String,
+    tenant_id: String,
+    expires_at: Option<String>,
+}
+
+/// Validates personal access tokens against D1.
+///
+/// Lookups are cached by token hash. The cache is bounded: once
+/// `max_entries` is reached the least recently used entry is evicted,
+/// whether it was a valid or an invalid token. Expired entries are never
+/// returned, and are physically removed by [`PatValidator::spawn_sweeper`].
+pub struct PatValidator {
+    client: reqwest::Client,
+    d1: D1Config,
+    cache: Cache<String, CachedPat>,
+}
+
+impl PatValidator {
+    /// Create a new validator.
+    pub fn new(d1: D1Config, cache_config: PatCacheConfig) -> Self {
+        let cache = Cache::builder()
+            .max_capacity(cache_config.max_entries)
+            .eviction_policy(EvictionPolicy::lru())
+            .expire_after(PatExpiry {
+                ttl: cache_config.ttl,
+                negative_ttl: cache_config.negative_ttl,
+            })
+            .build();
+
+        Self {
+            client: reqwest::Client::new(),
+            d1,
+            cache,
+        }
+    }
+
+    /// Validate a raw bearer token, returning the tenant it belongs to.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn validate(&self, token: &str) -> Result<PatInfo, ProxyError> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return Err(ProxyError::InvalidToken);
+        }
+
+        let token_hash = hash_token(token);
+        let outcome = match self.cache.get(&token_hash).await {
+            Some(cached) => cached,
+            None => {
+                // D1 failures are not cached: the next request retries.
+                let outcome = self.lookup(&token_hash).await?;
+                self.cache.insert(token_hash, outcome.clone()).await;
+                outcome
+            }
+        };
+
+        match outcome {
+            CachedPat::Valid(info) => Ok(info),
+            CachedPat::Invalid => Err(ProxyError::InvalidToken),
+        }
+    }
+
+    /// Number of entries currently held by the cache (approximate).
+    pub fn cached_entries(&self) -> u64 {
+        self.cache.entry_count()
+    }
+
+    /// Spawn a background task that purges expired entries every `interval`.
+    ///
+    /// The task stops on its own once the validator is dropped.
+    pub fn spawn_sweeper(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let validator: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(validator) = validator.upgrade() else {
+                    break;
+                };
+                validator.cache.run_pending_tasks().await;
+                debug!("PAT cache sweep: {} entries", validator.cache.entry_count());
+            }
+        })
+    }
+
+    /// Look up a token hash in D1.
+    async fn lookup(&self, token_hash: &str) -> Result<CachedPat, ProxyError> {
+        let body = serde_json::json!({
+            "sql": "SELECT id, tenantId, expiresAt FROM personal_access_token WHERE tokenHash = ?1 LIMIT 1",
+            "params": [token_hash],
+        });
+
+        let response = self
+            .client
+            .post(self.d1.query_url())
+            .bearer_auth(&self.d1.api_token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ProxyError::D1(format!("Request failed: {}", e)))?;
+
+        let status = response.status();
+        let response: D1Response = response
+            .json()
+            .await
+            .map_err(|e| ProxyError::D1(format!("Invalid response ({}): {}", status, e)))?;
+
+        if !response.success {
+            let messages: Vec<_> = response.errors.into_iter().map(|e| e.message).collect();
+            return Err(ProxyError::D1(messages.join("; ")));
+        }
+
+        let Some(row) = response
+            .result
+            .into_iter()
+            .next()
+            .and_then(|r| r.results.into_iter().next())
+        else {
+            return Ok(CachedPat::Invalid);
+        };
+
+        if let Some(expires_at) = row.expires_at.as_deref() {
+            match chrono::DateTime::parse_from_rfc3339(expires_at) {
+                Ok(expires_at) if expires_at < chrono::Utc::now() => {
+                    debug!("PAT {} expired at {}", row.id, expires_at);
+                    return Ok(CachedPat::Invalid);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Unparseable expiresAt for PAT {}: {}", row.id, e);
+                    return Ok(CachedPat::Invalid);
+                }
+            }
+        }
+
+        Ok(CachedPat::Valid(PatInfo {
+            pat_id: row.id,
+            tenant_id: row.tenant_id,
+        }))
+    }
+}
+
+/// SHA-256 hex digest of a token, matching the website's `hashToken`.
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator(max_entries: u64) -> PatValidator {
+        let d1 = D1Config {
+            api_base: CLOUDFLARE_API_BASE.to_string(),
+            account_id: "account".to_string(),
+            api_token: "token".to_string(),
+            database_id: "db".to_string(),
+        };
+        PatValidator::new(
+            d1,
+            PatCacheConfig {
+                ttl: Duration::from_secs(300),
+                negative_ttl: Duration::from_secs(60),
+                max_entries,
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_cache_cap_evicts_oldest() {
+        let validator = validator(10);
+
+        let valid = PatInfo {
+            pat_id: "pat-1".to_string(),
+            tenant_id: "tenant-1".to_string(),
+        };
+        validator
+            .cache
+            .insert("valid".to_string(), CachedPat::Valid(valid.clone()))
+            .await;
+
+        // Flood with invalid tokens, while the valid one keeps being used
+        for i in 0..25 {
+            validator
+                .cache
+                .insert(format!("invalid-{}", i), CachedPat::Invalid)
+                .await;
+            validator.cache.run_pending_tasks().await;
+            assert!(validator.cache.get("valid").await.is_some());
+        }
+        validator.cache.run_pending_tasks().await;
+
+        assert!(validator.cached_entries() <= 10);
+        assert!(!validator.cache.contains_key("invalid-0"));
+        assert!(!validator.cache.contains_key("invalid-10"));
+        assert!(validator.cache.contains_key("invalid-24"));
+        assert!(matches!(
+            validator.cache.get("valid").await,
+            Some(CachedPat::Valid(info)) if info == valid
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_negative_entries_expire() {
+        let validator = PatValidator::new(
+            validator(10).d1,
+            PatCacheConfig {
+                ttl: Duration::from_secs(300),
+                negative_ttl: Duration::from_millis(50),
+                max_entries: 10,
+            },
+        );
+
+        validator
+            .cache
+            .insert("invalid".to_string(), CachedPat::Invalid)
+            .await;
+        assert!(validator.cache.get("invalid").await.is_some());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(validator.cache.get("invalid").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unprefixed_token() {
+        let validator = validator(10);
+        let result = validator.validate("not-a-pat").await;
+        assert!(matches!(result, Err(ProxyError::InvalidToken)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 /// Configuration for the docx-mcp-proxy server.
@@ -5,14 +7,22 @@ use clap::Parser;
 #[command(name = "docx-mcp-proxy")]
 #[command(about = "SSE/HTTP proxy for docx-mcp multi-tenant architecture")]
 pub struct Config {
-    /// Host to bind to
+    /// Transport type: tcp or unix
+    #[arg(long, default_value = "tcp", env = "PROXY_TRANSPORT")]
+    pub transport: Transport,
+
+    /// Host to bind to (only used with --transport tcp)
     #[arg(long, default_value = "0.0.0.0", env = "PROXY_HOST")]
     pub host: String,
 
-    /// Port to bind to
+    /// Port to bind to (only used with --transport tcp)
     #[arg(long, default_value = "8080", env = "PROXY_PORT")]
     pub port: u16,
 
+    /// Unix socket path (only used with --transport unix)
+    #[arg(long, env = "PROXY_SOCKET_PATH")]
+    pub socket_path: Option<PathBuf>,
+
     /// Path to docx-mcp binary
     #[arg(long, env = "DOCX_MCP_BINARY")]
     pub docx_mcp_binary: Option<String>,
@@ -37,7 +47,42 @@ pub struct Config {
     #[arg(long, default_value = "60", env = "PAT_NEGATIVE_CACHE_TTL_SECS")]
     pub pat_negative_cache_ttl_secs: u64,
 
+    /// Maximum number of cached PAT lookups (valid and invalid combined)
+    #[arg(long, default_value = "10000", env = "PAT_CACHE_MAX_ENTRIES")]
+    pub pat_cache_max_entries: u64,
+
+    /// Close MCP sessions after this many seconds without a request
+    #[arg(long, default_value = "1800", env = "PROXY_SESSION_IDLE_TIMEOUT_SECS")]
+    pub session_idle_timeout_secs: u64,
+
     /// gRPC storage server URL
     #[arg(long, env = "STORAGE_GRPC_URL")]
     pub storage_grpc_url: Option<String>,
 }
+
+impl Config {
+    /// Get the effective Unix socket path.
+    pub fn effective_socket_path(&self) -> PathBuf {
+        self.socket_path.clone().unwrap_or_else(|| {
+            std::env::var("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| PathBuf::from("/tmp"))
+                .join("docx-mcp-proxy.sock")
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Transport {
+    Tcp,
+    Unix,
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transport::Tcp => write!(f, "tcp"),
+            Transport::Unix => write!(f, "unix"),
+        }
+    }
+}
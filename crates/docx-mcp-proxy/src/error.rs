@@ -0,0 +1,51 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use thiserror::Error;
+
+/// Errors that can occur in the proxy.
+#[derive(Error, Debug)]
+pub enum ProxyError {
+    #[error("Missing configuration: {0}")]
+    Config(String),
+
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("D1 error: {0}")]
+    D1(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("MCP process error: {0}")]
+    Mcp(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl ProxyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ProxyError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ProxyError::InvalidToken => StatusCode::UNAUTHORIZED,
+            ProxyError::D1(_) => StatusCode::BAD_GATEWAY,
+            ProxyError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ProxyError::SessionNotFound(_) => StatusCode::NOT_FOUND,
+            ProxyError::Mcp(_) => StatusCode::BAD_GATEWAY,
+            ProxyError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = Json(serde_json::json!({ "error": self.to_string() }));
+        (status, body).into_response()
+    }
+}
@@ -0,0 +1,7 @@
+//! Library side of the docx-mcp proxy, shared by the binary and its tests.
+
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod mcp;
+pub mod server;
@@ -7,13 +7,24 @@
 //! - Forwards requests to MCP .NET process via stdio
 //! - Streams responses back to clients
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use clap::Parser;
+use tokio::net::TcpListener;
+use tokio::signal;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
-mod config;
+use docx_mcp_proxy::auth::{D1Config, PatCacheConfig, PatValidator};
+use docx_mcp_proxy::config::{Config, Transport};
+use docx_mcp_proxy::server::{router, AppState, McpLauncher};
+
+/// Interval between sweeps of expired PAT cache entries.
+const PAT_CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
-use config::Config;
+/// Interval between checks for idle MCP sessions.
+const SESSION_REAP_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -27,15 +38,82 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::parse();
 
     info!("Starting docx-mcp-proxy");
-    info!("  Host: {}", config.host);
-    info!("  Port: {}", config.port);
+    info!("  Transport: {}", config.transport);
+
+    let launcher = McpLauncher::from_config(&config)?;
+    info!("  MCP binary: {}", launcher.binary);
+
+    let cache_config = PatCacheConfig::from_config(&config);
+    info!("  PAT cache max entries: {}", cache_config.max_entries);
+    let validator = Arc::new(PatValidator::new(D1Config::from_config(&config)?, cache_config));
+    let _sweeper = validator.spawn_sweeper(PAT_CACHE_SWEEP_INTERVAL);
+
+    let state = Arc::new(AppState::new(validator, launcher));
+    let idle_timeout = Duration::from_secs(config.session_idle_timeout_secs);
+    info!("  Session idle timeout: {}s", idle_timeout.as_secs());
+    let _reaper = state.spawn_session_reaper(idle_timeout, SESSION_REAP_INTERVAL);
+
+    let app = router(state);
+
+    match config.transport {
+        Transport::Tcp => {
+            let addr = format!("{}:{}", config.host, config.port);
+            let listener = TcpListener::bind(&addr).await?;
+            info!("Listening on http://{}", addr);
 
-    // TODO: Implement proxy server
-    // - D1 client for PAT validation
-    // - MCP process spawning and stdio bridge
-    // - Streamable HTTP endpoint
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+        }
+        #[cfg(unix)]
+        Transport::Unix => {
+            let socket_path = config.effective_socket_path();
+            let listener = docx_mcp_proxy::server::bind_unix_socket(&socket_path)?;
+            info!("Listening on unix://{}", socket_path.display());
 
-    info!("Proxy not yet implemented");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
 
+            // Clean up socket on shutdown
+            if socket_path.exists() {
+                let _ = std::fs::remove_file(&socket_path);
+            }
+        }
+        #[cfg(not(unix))]
+        Transport::Unix => {
+            anyhow::bail!("Unix socket transport is not supported on this platform");
+        }
+    }
+
+    info!("Proxy shutdown complete");
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {
+            info!("Received Ctrl+C, initiating shutdown");
+        },
+        _ = terminate => {
+            info!("Received SIGTERM, initiating shutdown");
+        },
+    }
+}
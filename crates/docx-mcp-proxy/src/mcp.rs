@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tracing::{debug, instrument, warn};
+
+use crate::error::ProxyError;
+
+/// A request waiting for its response, keyed by the id sent to the child.
+struct PendingRequest {
+    /// The id the client used, restored on the response.
+    client_id: Value,
+    sender: oneshot::Sender<Value>,
+}
+
+type PendingMap = Arc<Mutex<HashMap<u64, PendingRequest>>>;
+
+/// Number of unsolicited messages buffered for slow subscribers.
+const NOTIFICATION_CAPACITY: usize = 64;
+
+/// A spawned MCP server speaking JSON-RPC over stdio.
+///
+/// Messages are newline-delimited JSON. Requests are forwarded under an id
+/// allocated by the proxy, so concurrent requests reusing the same client id
+/// cannot be confused; responses read from the child's stdout are matched by
+/// that id and handed back with the client's id restored. Everything else
+/// the child writes (notifications and its own requests) is published to
+/// subscribers, see [`McpProcess::subscribe`].
+pub struct McpProcess {
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    pending: PendingMap,
+    next_id: AtomicU64,
+    notifications: broadcast::Sender<Value>,
+}
+
+impl McpProcess {
+    /// Spawn `binary` with the given extra environment variables.
+    pub fn spawn(binary: &str, envs: &[(&str, &str)]) -> Result<Self, ProxyError> {
+        let mut child = Command::new(binary)
+            .envs(envs.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| ProxyError::Mcp(format!("Failed to spawn {}: {}", binary, e)))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| ProxyError::Mcp("MCP process has no stdin".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| ProxyError::Mcp("MCP process has no stdout".to_string()))?;
+
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        tokio::spawn(read_loop(stdout, pending.clone(), notifications.clone()));
+
+        debug!("Spawned MCP process {} (pid {:?})", binary, child.id());
+        Ok(Self {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            pending,
+            next_id: AtomicU64::new(1),
+            notifications,
+        })
+    }
+
+    /// Receive the messages the child sends that are not responses.
+    ///
+    /// Only messages written after subscribing are received; they are
+    /// dropped when nobody is subscribed.
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.notifications.subscribe()
+    }
+
+    /// Send a JSON-RPC message to the child.
+    ///
+    /// For requests, waits up to `timeout` for the matching response.
+    /// Notifications and responses return `None` as soon as they are written.
+    #[instrument(skip(self, message), level = "debug")]
+    pub async fn send(
+        &self,
+        message: &Value,
+        timeout: Duration,
+    ) -> Result<Option<Value>, ProxyError> {
+        let client_id = match (message.get("method"), message.get("id")) {
+            (Some(_), Some(id)) if !id.is_null() => Some(id.clone()),
+            _ => None,
+        };
+
+        let mut forwarded;
+        let (message, request) = match client_id {
+            Some(client_id) => {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                forwarded = message.clone();
+                forwarded["id"] = id.into();
+
+                let (sender, receiver) = oneshot::channel();
+                let request = PendingRequest { client_id, sender };
+                self.pending.lock().await.insert(id, request);
+                (&forwarded, Some((id, receiver)))
+            }
+            None => (message, None),
+        };
+
+        let mut line = serde_json::to_vec(message)
+            .map_err(|e| ProxyError::Internal(format!("Failed to serialize message: {}", e)))?;
+        line.push(b'\n');
+
+        {
+            let mut stdin = self.stdin.lock().await;
+            let written = match stdin.write_all(&line).await {
+                Ok(()) => stdin.flush().await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                if let Some((id, _)) = &request {
+                    self.pending.lock().await.remove(id);
+                }
+                return Err(ProxyError::Mcp(format!("Failed to write to MCP process: {}", e)));
+            }
+        }
+
+        let Some((id, receiver)) = request else {
+            return Ok(None);
+        };
+
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(response)) => Ok(Some(response)),
+            Ok(Err(_)) => Err(ProxyError::Mcp("MCP process exited".to_string())),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                Err(ProxyError::Mcp(format!("Timed out waiting for response {}", id)))
+            }
+        }
+    }
+
+    /// Kill the child process.
+    pub async fn shutdown(&self) {
+        if let Err(e) = self.child.lock().await.kill().await {
+            warn!("Failed to kill MCP process: {}", e);
+        }
+    }
+}
+
+/// Dispatch responses from the child's stdout to waiting requests, and
+/// publish everything else to subscribers.
+async fn read_loop(
+    stdout: ChildStdout,
+    pending: PendingMap,
+    notifications: broadcast::Sender<Value>,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read from MCP process: {}", e);
+                break;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Ignoring non JSON-RPC output from MCP process: {}", e);
+                continue;
+            }
+        };
+
+        let is_response = message.get("result").is_some() || message.get("error").is_some();
+        let id = message.get("id").and_then(Value::as_u64);
+
+        match id {
+            Some(id) if is_response => match pending.lock().await.remove(&id) {
+                Some(request) => {
+                    message["id"] = request.client_id;
+                    let _ = request.sender.send(message);
+                }
+                None => debug!("Dropping response {} with no waiting request", id),
+            },
+            _ if is_response => debug!("Dropping response without a proxy id"),
+            _ => {
+                if notifications.send(message).is_err() {
+                    debug!("Dropping unsolicited message from MCP process: no subscriber");
+                }
+            }
+        }
+    }
+
+    // Dropping the senders wakes up every waiting request with an error
+    pending.lock().await.clear();
+    debug!("MCP process stdout closed");
+}
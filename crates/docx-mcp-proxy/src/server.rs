@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::header::{ACCEPT, AUTHORIZATION};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tower_http::trace::TraceLayer;
+use tracing::{debug, field, info, instrument, warn, Instrument, Span};
+
+use crate::auth::PatValidator;
+use crate::config::Config;
+use crate::error::ProxyError;
+use crate::mcp::McpProcess;
+
+/// Header carrying the Streamable HTTP session id.
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the id used to correlate a request across the proxy and
+/// the MCP process.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request id that is honored.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// JSON-RPC error code reported when the MCP process fails to answer.
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Maximum time to wait for the MCP process to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How to launch the MCP process backing each session.
+#[derive(Debug, Clone)]
+pub struct McpLauncher {
+    /// Path to the docx-mcp binary.
+    pub binary: String,
+    /// gRPC storage server URL forwarded to the child, if any.
+    pub storage_grpc_url: Option<String>,
+}
+
+impl McpLauncher {
+    pub fn from_config(config: &Config) -> Result<Self, ProxyError> {
+        let binary = config
+            .docx_mcp_binary
+            .clone()
+            .filter(|b| !b.is_empty())
+            .ok_or_else(|| ProxyError::Config("--docx-mcp-binary is required".to_string()))?;
+
+        Ok(Self {
+            binary,
+            storage_grpc_url: config.storage_grpc_url.clone(),
+        })
+    }
+
+    fn spawn(&self, tenant_id: &str) -> Result<McpProcess, ProxyError> {
+        let mut envs = vec![("DOCX_TENANT_ID", tenant_id)];
+        if let Some(url) = &self.storage_grpc_url {
+            envs.push(("STORAGE_GRPC_URL", url.as_str()));
+        }
+        McpProcess::spawn(&self.binary, &envs)
+    }
+}
+
+/// An MCP session: one child process, owned by one tenant.
+struct McpSession {
+    tenant_id: String,
+    process: McpProcess,
+    last_active: Mutex<Instant>,
+}
+
+impl McpSession {
+    fn new(tenant_id: String, process: McpProcess) -> Self {
+        Self {
+            tenant_id,
+            process,
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Record activity on the session, postponing its idle expiry.
+    fn touch(&self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_active.lock().unwrap().elapsed()
+    }
+}
+
+/// Shared state of the HTTP server.
+pub struct AppState {
+    validator: Arc<PatValidator>,
+    launcher: McpLauncher,
+    sessions: RwLock<HashMap<String, Arc<McpSession>>>,
+}
+
+impl AppState {
+    pub fn new(validator: Arc<PatValidator>, launcher: McpLauncher) -> Self {
+        Self {
+            validator,
+            launcher,
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Validate the bearer token and return the tenant it belongs to.
+    async fn authenticate(&self, headers: &HeaderMap) -> Result<String, ProxyError> {
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(ProxyError::InvalidToken)?;
+
+        let pat = self.validator.validate(token.trim()).await?;
+        Ok(pat.tenant_id)
+    }
+
+    /// Find the session named in the request headers, checking tenant ownership.
+    async fn session(
+        &self,
+        headers: &HeaderMap,
+        tenant_id: &str,
+    ) -> Result<Option<(String, Arc<McpSession>)>, ProxyError> {
+        let Some(session_id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+            return Ok(None);
+        };
+
+        let sessions = self.sessions.read().await;
+        match sessions.get(session_id) {
+            // Another tenant's session is reported as missing, not forbidden
+            Some(session) if session.tenant_id == tenant_id => {
+                session.touch();
+                Ok(Some((session_id.to_string(), session.clone())))
+            }
+            _ => Err(ProxyError::SessionNotFound(session_id.to_string())),
+        }
+    }
+
+    /// Close every session unused for at least `idle_timeout`, returning how
+    /// many were closed.
+    ///
+    /// Activity is recorded when a request starts and when its response is
+    /// ready, so a request outlasting `idle_timeout` can see its session
+    /// closed under it; keep the timeout above the request timeout.
+    pub async fn reap_idle_sessions(&self, idle_timeout: Duration) -> usize {
+        let mut idle = Vec::new();
+        self.sessions.write().await.retain(|session_id, session| {
+            if session.idle_for() < idle_timeout {
+                return true;
+            }
+            idle.push((session_id.clone(), session.clone()));
+            false
+        });
+
+        for (session_id, session) in &idle {
+            session.process.shutdown().await;
+            info!(
+                "Closed idle MCP session {} for tenant {}",
+                session_id, session.tenant_id
+            );
+        }
+        idle.len()
+    }
+
+    /// Spawn a background task that closes idle sessions every `interval`.
+    ///
+    /// The task stops on its own once the state is dropped.
+    pub fn spawn_session_reaper(
+        self: &Arc<Self>,
+        idle_timeout: Duration,
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let state: Weak<Self> = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                state.reap_idle_sessions(idle_timeout).await;
+            }
+        })
+    }
+}
+
+/// Bind a Unix socket listener, replacing a stale socket file left behind
+/// by a previous run and creating the parent directory if needed.
+#[cfg(unix)]
+pub fn bind_unix_socket(path: &std::path::Path) -> std::io::Result<tokio::net::UnixListener> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    tokio::net::UnixListener::bind(path)
+}
+
+/// Build the proxy's HTTP router.
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/mcp", post(mcp_post).delete(mcp_delete))
+        .route("/health", get(health))
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+#[instrument(skip_all, fields(request_id = field::Empty))]
+async fn mcp_post(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(message): Json<Value>,
+) -> Response {
+    let request_id = request_id(&headers);
+    Span::current().record("request_id", request_id.as_str());
+
+    let mut response = match forward(&state, &headers, message, &request_id).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Forward a client message to its session's MCP process, starting the
+/// session on `initialize`.
+async fn forward(
+    state: &Arc<AppState>,
+    headers: &HeaderMap,
+    mut message: Value,
+    request_id: &str,
+) -> Result<Response, ProxyError> {
+    let tenant_id = state.authenticate(headers).await?;
+
+    if !message.is_object() {
+        return Err(ProxyError::BadRequest(
+            "expected a single JSON-RPC message".to_string(),
+        ));
+    }
+
+    attach_request_id(&mut message, request_id);
+
+    let (session_id, session, created) = match state.session(headers, &tenant_id).await? {
+        Some((session_id, session)) => (session_id, session, false),
+        None => {
+            if message.get("method").and_then(Value::as_str) != Some("initialize") {
+                return Err(ProxyError::BadRequest(format!(
+                    "{} header is required",
+                    SESSION_HEADER
+                )));
+            }
+
+            let process = state.launcher.spawn(&tenant_id)?;
+            let session = Arc::new(McpSession::new(tenant_id.clone(), process));
+            let session_id = uuid::Uuid::new_v4().to_string();
+            state
+                .sessions
+                .write()
+                .await
+                .insert(session_id.clone(), session.clone());
+
+            info!("Started MCP session {} for tenant {}", session_id, tenant_id);
+            (session_id, session, true)
+        }
+    };
+
+    let is_request = message.get("method").is_some()
+        && message.get("id").is_some_and(|id| !id.is_null());
+    let mut response = if is_request && accepts_event_stream(headers) {
+        let stream = stream_reply(state.clone(), session_id.clone(), session, created, message);
+        Sse::new(stream).into_response()
+    } else {
+        let reply = match session.process.send(&message, REQUEST_TIMEOUT).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                if created {
+                    state.sessions.write().await.remove(&session_id);
+                    session.process.shutdown().await;
+                }
+                return Err(e);
+            }
+        };
+        session.touch();
+
+        match reply {
+            None => StatusCode::ACCEPTED.into_response(),
+            Some(reply) => Json(reply).into_response(),
+        }
+    };
+
+    let session_header = HeaderValue::from_str(&session_id)
+        .map_err(|e| ProxyError::Internal(format!("Invalid session id: {}", e)))?;
+    response.headers_mut().insert(SESSION_HEADER, session_header);
+    Ok(response)
+}
+
+/// Send a request to the session's MCP process and stream what it writes
+/// back as server-sent events.
+///
+/// Notifications and requests from the process are relayed as they arrive
+/// while the request is in flight, and the stream ends with its response.
+/// Failures are reported as a JSON-RPC error response, since the HTTP
+/// status has already been sent.
+fn stream_reply(
+    state: Arc<AppState>,
+    session_id: String,
+    session: Arc<McpSession>,
+    created: bool,
+    message: Value,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    let (tx, rx) = mpsc::channel(16);
+    let mut notifications = session.process.subscribe();
+
+    let relay = async move {
+        let send = session.process.send(&message, REQUEST_TIMEOUT);
+        tokio::pin!(send);
+        let reply = loop {
+            tokio::select! {
+                reply = &mut send => break reply,
+                notification = notifications.recv() => match notification {
+                    Ok(notification) => {
+                        let _ = tx.send(notification).await;
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        warn!("Dropped {} messages from MCP session {}", count, session_id);
+                    }
+                    Err(RecvError::Closed) => break (&mut send).await,
+                },
+            }
+        };
+
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                warn!("Request on MCP session {} failed: {}", session_id, e);
+                if created {
+                    state.sessions.write().await.remove(&session_id);
+                    session.process.shutdown().await;
+                }
+                Some(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": INTERNAL_ERROR, "message": e.to_string() },
+                }))
+            }
+        };
+        session.touch();
+
+        if let Some(reply) = reply {
+            let _ = tx.send(reply).await;
+        }
+    };
+    tokio::spawn(relay.instrument(Span::current()));
+
+    ReceiverStream::new(rx).map(|message| Event::default().event("message").json_data(message))
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn mcp_delete(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ProxyError> {
+    let tenant_id = state.authenticate(&headers).await?;
+
+    let Some((session_id, session)) = state.session(&headers, &tenant_id).await? else {
+        return Err(ProxyError::BadRequest(format!(
+            "{} header is required",
+            SESSION_HEADER
+        )));
+    };
+
+    state.sessions.write().await.remove(&session_id);
+    session.process.shutdown().await;
+
+    debug!("Closed MCP session {} for tenant {}", session_id, tenant_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn health(State(state): State<Arc<AppState>>) -> Json<Value> {
+    Json(serde_json::json!({
+        "healthy": true,
+        "sessions": state.sessions.read().await.len(),
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"))
+}
+
+/// Use the client's request id if it is a reasonable token, otherwise
+/// generate one.
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Pass the request id to the MCP process as `params._meta.requestId`.
+///
+/// Only requests and notifications carry params; messages whose params are
+/// positional are left untouched.
+fn attach_request_id(message: &mut Value, request_id: &str) {
+    if message.get("method").is_none() {
+        return;
+    }
+    let Some(message) = message.as_object_mut() else {
+        return;
+    };
+    let params = message
+        .entry("params")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Some(params) = params.as_object_mut() else {
+        return;
+    };
+    let meta = params
+        .entry("_meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    if let Some(meta) = meta.as_object_mut() {
+        meta.insert("requestId".to_string(), Value::String(request_id.to_string()));
+    }
+}
@@ -0,0 +1,388 @@
+//! End-to-end tests of the proxy against a fake D1 API and a fake MCP child.
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+use docx_mcp_proxy::auth::{D1Config, PatCacheConfig, PatValidator};
+use docx_mcp_proxy::server::{
+    bind_unix_socket, router, AppState, McpLauncher, REQUEST_ID_HEADER, SESSION_HEADER,
+};
+
+const VALID_TOKEN: &str = "dxs_0123456789abcdef";
+
+/// An MCP "server" that answers every request with its id, tenant and the
+/// request id found in `params._meta`, reporting progress on tool calls.
+const FAKE_MCP: &str = r#"#!/bin/sh
+while IFS= read -r line; do
+  case "$line" in
+    *'"tools/call"'*)
+      printf '{"jsonrpc":"2.0","method":"notifications/progress","params":{"progress":1}}\n' ;;
+  esac
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9][0-9]*\).*/\1/p')
+  request_id=$(printf '%s' "$line" | sed -n 's/.*"requestId":"\([^"]*\)".*/\1/p')
+  if [ -n "$id" ]; then
+    printf '{"jsonrpc":"2.0","id":%s,"result":{"echo":true,"tenant":"%s","requestId":"%s"}}\n' \
+      "$id" "$DOCX_TENANT_ID" "$request_id"
+  fi
+done
+"#;
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+/// Fake D1 query endpoint: only the hash of `VALID_TOKEN` is known.
+async fn fake_d1() -> String {
+    let valid_hash = {
+        use sha2::{Digest, Sha256};
+        hex::encode(Sha256::digest(VALID_TOKEN.as_bytes()))
+    };
+
+    let app = Router::new().route(
+        "/accounts/{account}/d1/database/{db}/query",
+        post(move |Json(body): Json<Value>| {
+            let valid_hash = valid_hash.clone();
+            async move {
+                let results = if body["params"][0] == valid_hash {
+                    json!([{ "id": "pat-1", "tenantId": "tenant-a", "expiresAt": null }])
+                } else {
+                    json!([])
+                };
+                Json(json!({ "success": true, "result": [{ "results": results }] }))
+            }
+        }),
+    );
+    serve(app).await
+}
+
+fn proxy_state(temp: &TempDir, d1_base: String) -> Arc<AppState> {
+    let script = temp.path().join("fake-mcp.sh");
+    std::fs::write(&script, FAKE_MCP).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let d1 = D1Config {
+        api_base: d1_base,
+        account_id: "account".to_string(),
+        api_token: "token".to_string(),
+        database_id: "db".to_string(),
+    };
+    let cache = PatCacheConfig {
+        ttl: Duration::from_secs(300),
+        negative_ttl: Duration::from_secs(60),
+        max_entries: 100,
+    };
+    let launcher = McpLauncher {
+        binary: script.to_string_lossy().to_string(),
+        storage_grpc_url: None,
+    };
+
+    Arc::new(AppState::new(Arc::new(PatValidator::new(d1, cache)), launcher))
+}
+
+async fn start_proxy(temp: &TempDir) -> String {
+    let state = proxy_state(temp, fake_d1().await);
+    serve(router(state)).await
+}
+
+fn initialize() -> Value {
+    json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })
+}
+
+/// Start a session and return its id.
+async fn start_session(client: &reqwest::Client, base: &str) -> String {
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    response.headers()[SESSION_HEADER].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_mcp_round_trip() {
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+
+    // Initialize starts a session
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let session_id = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["id"], 1);
+    assert_eq!(body["result"]["tenant"], "tenant-a");
+
+    // Follow-up request on the same session, streamed as SSE
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, &session_id)
+        .header("accept", "application/json, text/event-stream")
+        .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("event: message"));
+    assert!(body.contains(r#""id":2"#));
+
+    // Notifications are accepted without a body
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, &session_id)
+        .json(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 202);
+
+    // Closing the session
+    let response = client
+        .delete(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, &session_id)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+}
+
+#[tokio::test]
+async fn test_concurrent_requests_with_same_id() {
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+    let session_id = start_session(&client, &base).await;
+
+    let request = || {
+        client
+            .post(format!("{}/mcp", base))
+            .bearer_auth(VALID_TOKEN)
+            .header(SESSION_HEADER, &session_id)
+            .json(&json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/list" }))
+            .send()
+    };
+    let (first, second) = tokio::join!(request(), request());
+
+    for response in [first.unwrap(), second.unwrap()] {
+        assert_eq!(response.status(), 200);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["id"], 7);
+    }
+}
+
+#[tokio::test]
+async fn test_streams_notifications_before_response() {
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+    let session_id = start_session(&client, &base).await;
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, &session_id)
+        .header("accept", "application/json, text/event-stream")
+        .json(&json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {} }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let body = response.text().await.unwrap();
+    let events: Vec<Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["method"], "notifications/progress");
+    assert_eq!(events[1]["id"], 3);
+}
+
+#[tokio::test]
+async fn test_idle_sessions_are_closed() {
+    let temp = TempDir::new().unwrap();
+    let state = proxy_state(&temp, fake_d1().await);
+    let base = serve(router(state.clone())).await;
+    let client = reqwest::Client::new();
+    let session_id = start_session(&client, &base).await;
+
+    // Recently used sessions are kept
+    assert_eq!(state.reap_idle_sessions(Duration::from_secs(60)).await, 0);
+    assert_eq!(state.reap_idle_sessions(Duration::ZERO).await, 1);
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, &session_id)
+        .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn test_rejects_invalid_token() {
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth("dxs_unknown")
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn test_requires_session_after_initialize() {
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(SESSION_HEADER, "no-such-session")
+        .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+/// Log output captured by a test subscriber.
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_request_id_propagation() {
+    // The proxy runs on this (current-thread) runtime, so it logs here
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let temp = TempDir::new().unwrap();
+    let base = start_proxy(&temp).await;
+    let client = reqwest::Client::new();
+
+    // A client-supplied id reaches the MCP process and comes back
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth(VALID_TOKEN)
+        .header(REQUEST_ID_HEADER, "req-42")
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-42");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["result"]["requestId"], "req-42");
+
+    // The proxy's own logs for the request carry the id
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let line = logs
+        .lines()
+        .find(|l| l.contains("Started MCP session"))
+        .unwrap_or_else(|| panic!("proxy did not log the session start:\n{}", logs));
+    assert!(line.contains("request_id=\"req-42\""), "{}", line);
+
+    // Otherwise one is generated, also on errors
+    let response = client
+        .post(format!("{}/mcp", base))
+        .bearer_auth("dxs_unknown")
+        .json(&initialize())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(request_id).is_ok());
+}
+
+#[tokio::test]
+async fn test_unix_socket_health() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let temp = TempDir::new().unwrap();
+    let socket_path = temp.path().join("run").join("proxy.sock");
+    std::fs::create_dir_all(socket_path.parent().unwrap()).unwrap();
+    // A stale socket file from a previous run is replaced
+    std::fs::write(&socket_path, b"").unwrap();
+
+    let listener = bind_unix_socket(&socket_path).unwrap();
+    let app = router(proxy_state(&temp, "http://127.0.0.1:9".to_string()));
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let mut stream = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains(r#""healthy":true"#));
+}
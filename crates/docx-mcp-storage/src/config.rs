@@ -2,6 +2,18 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+/// Default chunk size for gRPC streaming: 256KB
+pub const DEFAULT_GRPC_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// Smallest accepted chunk size: 16KB
+pub const MIN_GRPC_CHUNK_SIZE: u64 = 16 * 1024;
+
+/// Largest accepted chunk size: 4MB (tonic's default max message size)
+pub const MAX_GRPC_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Default limit on the size of an imported session bundle: 256MB
+pub const DEFAULT_MAX_BUNDLE_SIZE: u64 = 256 * 1024 * 1024;
+
 /// Configuration for the docx-mcp-storage server.
 #[derive(Parser, Debug, Clone)]
 #[command(name = "docx-mcp-storage")]
@@ -23,6 +35,37 @@ pub struct Config {
     #[arg(long, env = "GRPC_UNIX_SOCKET")]
     pub unix_socket: Option<PathBuf>,
 
+    /// PEM server certificate; enables TLS (only used with --transport tcp)
+    #[arg(long, env = "GRPC_TLS_CERT", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, env = "GRPC_TLS_KEY", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// PEM CA bundle; when set, clients must present a certificate it signed
+    #[arg(long, env = "GRPC_TLS_CLIENT_CA", requires = "tls_cert")]
+    pub tls_client_ca: Option<PathBuf>,
+
+    /// Chunk size in bytes for streaming RPCs (16KB to 4MB)
+    #[arg(
+        long,
+        default_value_t = DEFAULT_GRPC_CHUNK_SIZE,
+        env = "GRPC_CHUNK_SIZE",
+        value_parser = clap::value_parser!(u64).range(MIN_GRPC_CHUNK_SIZE..=MAX_GRPC_CHUNK_SIZE)
+    )]
+    pub grpc_chunk_size: u64,
+
+    /// WAL entries since the last checkpoint after which append_wal
+    /// recommends a checkpoint (0 = never)
+    #[arg(long, default_value = "50", env = "WAL_CHECKPOINT_THRESHOLD")]
+    pub checkpoint_threshold: u64,
+
+    /// Largest session bundle ImportBundle accepts, in bytes. Bundles are
+    /// buffered in memory while they are imported.
+    #[arg(long, default_value_t = DEFAULT_MAX_BUNDLE_SIZE, env = "MAX_BUNDLE_SIZE")]
+    pub max_bundle_size: u64,
+
     /// Storage backend: local or r2
     #[arg(long, default_value = "local", env = "STORAGE_BACKEND")]
     pub storage_backend: StorageBackend,
@@ -31,6 +74,10 @@ pub struct Config {
     #[arg(long, env = "LOCAL_STORAGE_DIR")]
     pub local_storage_dir: Option<PathBuf>,
 
+    /// Fsync local writes before acknowledging them (slower, survives power loss)
+    #[arg(long, env = "LOCAL_STORAGE_DURABLE")]
+    pub durable_writes: bool,
+
     /// R2 endpoint URL (for r2 backend)
     #[arg(long, env = "R2_ENDPOINT")]
     pub r2_endpoint: Option<String>,
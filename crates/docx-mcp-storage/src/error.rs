@@ -12,6 +12,18 @@ pub enum StorageError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Too large: {0}")]
+    TooLarge(String),
+
+    #[error("Checksum mismatch: {0}")]
+    ChecksumMismatch(String),
+
     #[error("Lock error: {0}")]
     Lock(String),
 
@@ -22,15 +34,84 @@ pub enum StorageError {
     Internal(String),
 }
 
+impl StorageError {
+    /// Wrap an I/O error, keeping its kind where a dedicated variant exists.
+    pub fn io(err: std::io::Error, context: impl std::fmt::Display) -> Self {
+        let msg = format!("{}: {}", context, err);
+        match err.kind() {
+            std::io::ErrorKind::NotFound => StorageError::NotFound(msg),
+            std::io::ErrorKind::AlreadyExists => StorageError::Conflict(msg),
+            std::io::ErrorKind::PermissionDenied => StorageError::PermissionDenied(msg),
+            std::io::ErrorKind::StorageFull | std::io::ErrorKind::FileTooLarge => {
+                StorageError::TooLarge(msg)
+            }
+            _ => StorageError::Io(msg),
+        }
+    }
+}
+
 impl From<StorageError> for tonic::Status {
     fn from(err: StorageError) -> Self {
         match err {
             StorageError::Io(msg) => tonic::Status::internal(msg),
             StorageError::Serialization(msg) => tonic::Status::internal(msg),
             StorageError::NotFound(msg) => tonic::Status::not_found(msg),
+            StorageError::Conflict(msg) => tonic::Status::already_exists(msg),
+            StorageError::PermissionDenied(msg) => tonic::Status::permission_denied(msg),
+            StorageError::TooLarge(msg) => tonic::Status::resource_exhausted(msg),
+            StorageError::ChecksumMismatch(msg) => tonic::Status::data_loss(msg),
             StorageError::Lock(msg) => tonic::Status::failed_precondition(msg),
             StorageError::InvalidArgument(msg) => tonic::Status::invalid_argument(msg),
             StorageError::Internal(msg) => tonic::Status::internal(msg),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[test]
+    fn test_status_codes() {
+        let cases = [
+            (StorageError::Io("x".into()), Code::Internal),
+            (StorageError::Serialization("x".into()), Code::Internal),
+            (StorageError::NotFound("x".into()), Code::NotFound),
+            (StorageError::Conflict("x".into()), Code::AlreadyExists),
+            (
+                StorageError::PermissionDenied("x".into()),
+                Code::PermissionDenied,
+            ),
+            (StorageError::TooLarge("x".into()), Code::ResourceExhausted),
+            (StorageError::ChecksumMismatch("x".into()), Code::DataLoss),
+            (StorageError::Lock("x".into()), Code::FailedPrecondition),
+            (
+                StorageError::InvalidArgument("x".into()),
+                Code::InvalidArgument,
+            ),
+            (StorageError::Internal("x".into()), Code::Internal),
+        ];
+        for (err, code) in cases {
+            assert_eq!(tonic::Status::from(err).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_io_error_kinds() {
+        use std::io::{Error, ErrorKind};
+
+        let err = StorageError::io(Error::from(ErrorKind::PermissionDenied), "Failed to read x");
+        assert!(
+            matches!(err, StorageError::PermissionDenied(ref m) if m.starts_with("Failed to read x: "))
+        );
+        assert!(matches!(
+            StorageError::io(Error::from(ErrorKind::AlreadyExists), "x"),
+            StorageError::Conflict(_)
+        ));
+        assert!(matches!(
+            StorageError::io(Error::from(ErrorKind::Other), "x"),
+            StorageError::Io(_)
+        ));
+    }
+}
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tracing::{debug, instrument, warn};
 
-use super::traits::{LockAcquireResult, LockManager, LockReleaseResult, LockRenewResult};
+use super::traits::{
+    HolderProcess, LockAcquireResult, LockInfo, LockManager, LockReleaseResult, LockRenewResult,
+};
 use crate::error::StorageError;
 
 /// File-based lock manager for local deployments.
@@ -14,16 +16,26 @@ use crate::error::StorageError;
 /// Lock files are stored at:
 /// `{base_dir}/{tenant_id}/locks/{resource_id}.lock`
 ///
-/// Each lock file contains JSON with holder_id and expiration.
+/// Each lock file contains JSON with holder_id and expiration, plus the PID
+/// and hostname of the holder's process when the holder provided them. A
+/// contended lock whose holder ran on this host and has since died is taken
+/// over without waiting for the TTL; other locks only expire through the TTL.
 #[derive(Debug, Clone)]
 pub struct FileLock {
     base_dir: PathBuf,
+    hostname: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct LockFile {
     holder_id: String,
     expires_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    acquired_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hostname: Option<String>,
 }
 
 impl FileLock {
@@ -31,6 +43,50 @@ impl FileLock {
     pub fn new(base_dir: impl AsRef<Path>) -> Self {
         Self {
             base_dir: base_dir.as_ref().to_path_buf(),
+            hostname: HolderProcess::current().map(|p| p.hostname),
+        }
+    }
+
+    /// Build the lock file content for a holder running as `process`.
+    fn new_lock(
+        &self,
+        holder_id: &str,
+        ttl: Duration,
+        process: Option<&HolderProcess>,
+    ) -> LockFile {
+        let now = chrono::Utc::now().timestamp();
+        LockFile {
+            holder_id: holder_id.to_string(),
+            expires_at: now + ttl.as_secs() as i64,
+            acquired_at: Some(now),
+            pid: process.map(|p| p.pid),
+            hostname: process.map(|p| p.hostname.clone()),
+        }
+    }
+
+    /// Build the renewed lock file content of an existing lock, keeping its
+    /// acquisition time and, unless `process` is given, its process.
+    fn renewed_lock(
+        &self,
+        existing: LockFile,
+        ttl: Duration,
+        process: Option<&HolderProcess>,
+    ) -> LockFile {
+        let mut lock = self.new_lock(&existing.holder_id, ttl, process);
+        lock.acquired_at = existing.acquired_at.or(lock.acquired_at);
+        if process.is_none() {
+            lock.pid = existing.pid;
+            lock.hostname = existing.hostname;
+        }
+        lock
+    }
+
+    /// Whether a lock was written by a process on this host that is no
+    /// longer running.
+    fn holder_is_dead(&self, lock: &LockFile) -> bool {
+        match (lock.pid, &lock.hostname, &self.hostname) {
+            (Some(pid), Some(lock_host), Some(host)) if lock_host == host => !process_alive(pid),
+            _ => false,
         }
     }
 
@@ -48,7 +104,7 @@ impl FileLock {
     async fn ensure_locks_dir(&self, tenant_id: &str) -> Result<(), StorageError> {
         let dir = self.locks_dir(tenant_id);
         fs::create_dir_all(&dir).await.map_err(|e| {
-            StorageError::Io(format!("Failed to create locks dir {}: {}", dir.display(), e))
+            StorageError::io(e, format!("Failed to create locks dir {}", dir.display()))
         })?;
         Ok(())
     }
@@ -97,11 +153,11 @@ impl FileLock {
         })?;
 
         fs::write(&temp_path, &content).await.map_err(|e| {
-            StorageError::Io(format!("Failed to write lock file: {}", e))
+            StorageError::io(e, "Failed to write lock file")
         })?;
 
         fs::rename(&temp_path, &path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to rename lock file: {}", e))
+            StorageError::io(e, "Failed to rename lock file")
         })?;
 
         Ok(())
@@ -121,16 +177,13 @@ impl LockManager for FileLock {
         resource_id: &str,
         holder_id: &str,
         ttl: Duration,
+        process: Option<&HolderProcess>,
     ) -> Result<LockAcquireResult, StorageError> {
         // Check for existing lock
         if let Some(existing) = self.read_lock(tenant_id, resource_id).await {
             if existing.holder_id == holder_id {
                 // We already hold the lock, renew it
-                let expires_at = chrono::Utc::now().timestamp() + ttl.as_secs() as i64;
-                let lock = LockFile {
-                    holder_id: holder_id.to_string(),
-                    expires_at,
-                };
+                let lock = self.renewed_lock(existing, ttl, process);
                 self.write_lock(tenant_id, resource_id, &lock).await?;
 
                 debug!(
@@ -140,39 +193,44 @@ impl LockManager for FileLock {
                 return Ok(LockAcquireResult {
                     acquired: true,
                     current_holder: None,
-                    expires_at,
+                    expires_at: lock.expires_at,
                 });
             }
 
-            // Someone else holds the lock
-            debug!(
-                "Lock on {}/{} held by {} (requested by {})",
-                tenant_id, resource_id, existing.holder_id, holder_id
+            if !self.holder_is_dead(&existing) {
+                // Someone else holds the lock
+                debug!(
+                    "Lock on {}/{} held by {} (requested by {})",
+                    tenant_id, resource_id, existing.holder_id, holder_id
+                );
+                return Ok(LockAcquireResult {
+                    acquired: false,
+                    current_holder: Some(existing.holder_id),
+                    expires_at: existing.expires_at,
+                });
+            }
+
+            warn!(
+                "Taking over lock on {}/{} from {}: process {} is gone",
+                tenant_id,
+                resource_id,
+                existing.holder_id,
+                existing.pid.unwrap_or_default()
             );
-            return Ok(LockAcquireResult {
-                acquired: false,
-                current_holder: Some(existing.holder_id),
-                expires_at: existing.expires_at,
-            });
         }
 
-        // No lock exists, create one
-        let expires_at = chrono::Utc::now().timestamp() + ttl.as_secs() as i64;
-        let lock = LockFile {
-            holder_id: holder_id.to_string(),
-            expires_at,
-        };
-
+        // No live lock exists, create one
+        let lock = self.new_lock(holder_id, ttl, process);
         self.write_lock(tenant_id, resource_id, &lock).await?;
 
         debug!(
             "Acquired lock on {}/{} for {} (expires at {})",
-            tenant_id, resource_id, holder_id, expires_at
+            tenant_id, resource_id, holder_id, lock.expires_at
         );
         Ok(LockAcquireResult {
             acquired: true,
             current_holder: None,
-            expires_at,
+            expires_at: lock.expires_at,
         })
     }
 
@@ -201,7 +259,7 @@ impl LockManager for FileLock {
             // We hold the lock, delete it
             if let Err(e) = fs::remove_file(&path).await {
                 if e.kind() != std::io::ErrorKind::NotFound {
-                    return Err(StorageError::Io(format!("Failed to delete lock: {}", e)));
+                    return Err(StorageError::io(e, "Failed to delete lock"));
                 }
             }
 
@@ -230,6 +288,7 @@ impl LockManager for FileLock {
         resource_id: &str,
         holder_id: &str,
         ttl: Duration,
+        process: Option<&HolderProcess>,
     ) -> Result<LockRenewResult, StorageError> {
         if let Some(existing) = self.read_lock(tenant_id, resource_id).await {
             if existing.holder_id != holder_id {
@@ -245,20 +304,16 @@ impl LockManager for FileLock {
             }
 
             // We hold the lock, renew it
-            let expires_at = chrono::Utc::now().timestamp() + ttl.as_secs() as i64;
-            let lock = LockFile {
-                holder_id: holder_id.to_string(),
-                expires_at,
-            };
+            let lock = self.renewed_lock(existing, ttl, process);
             self.write_lock(tenant_id, resource_id, &lock).await?;
 
             debug!(
                 "Renewed lock on {}/{} for {} (new expiry: {})",
-                tenant_id, resource_id, holder_id, expires_at
+                tenant_id, resource_id, holder_id, lock.expires_at
             );
             return Ok(LockRenewResult {
                 renewed: true,
-                expires_at,
+                expires_at: lock.expires_at,
                 reason: "ok".to_string(),
             });
         }
@@ -274,6 +329,86 @@ impl LockManager for FileLock {
             reason: "not_found".to_string(),
         })
     }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn list_locks(&self, tenant_id: &str) -> Result<Vec<LockInfo>, StorageError> {
+        let dir = self.locks_dir(tenant_id);
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => {
+                return Err(StorageError::io(
+                    e,
+                    format!("Failed to read locks dir {}", dir.display()),
+                ))
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let mut locks = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| StorageError::io(e, "Failed to read locks dir entry"))?
+        {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(resource_id) = file_name.strip_suffix(".lock") else {
+                continue;
+            };
+
+            // Unlike read_lock, leave expired and corrupted files in place:
+            // listing is for inspection only.
+            let lock = match fs::read_to_string(entry.path()).await {
+                Ok(content) => match serde_json::from_str::<LockFile>(&content) {
+                    Ok(lock) => lock,
+                    Err(e) => {
+                        warn!("Skipping unreadable lock file {}: {}", file_name, e);
+                        continue;
+                    }
+                },
+                // Released while listing
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(StorageError::io(e, "Failed to read lock file")),
+            };
+
+            locks.push(LockInfo {
+                resource_id: resource_id.to_string(),
+                holder_id: lock.holder_id,
+                acquired_at: lock.acquired_at,
+                expires_at: lock.expires_at,
+                expired: lock.expires_at <= now,
+            });
+        }
+
+        locks.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+        Ok(locks)
+    }
+}
+
+/// Whether a process with this PID exists.
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    // PIDs that don't name a single process can't be checked; leave those
+    // locks to the TTL.
+    let Ok(pid) = i32::try_from(pid) else {
+        return true;
+    };
+    if pid <= 0 {
+        return true;
+    }
+
+    // Signal 0 only checks for existence. EPERM means the process exists
+    // but belongs to another user.
+    !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH))
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
 }
 
 #[cfg(test)]
@@ -296,12 +431,12 @@ mod tests {
         let ttl = Duration::from_secs(60);
 
         // Acquire lock
-        let result = lock_mgr.acquire(tenant, resource, holder, ttl).await.unwrap();
+        let result = lock_mgr.acquire(tenant, resource, holder, ttl, None).await.unwrap();
         assert!(result.acquired);
         assert!(result.current_holder.is_none());
 
         // Try to acquire same lock with different holder
-        let result2 = lock_mgr.acquire(tenant, resource, "holder-2", ttl).await.unwrap();
+        let result2 = lock_mgr.acquire(tenant, resource, "holder-2", ttl, None).await.unwrap();
         assert!(!result2.acquired);
         assert_eq!(result2.current_holder, Some(holder.to_string()));
 
@@ -311,7 +446,7 @@ mod tests {
         assert_eq!(release.reason, "ok");
 
         // Now holder-2 can acquire
-        let result3 = lock_mgr.acquire(tenant, resource, "holder-2", ttl).await.unwrap();
+        let result3 = lock_mgr.acquire(tenant, resource, "holder-2", ttl, None).await.unwrap();
         assert!(result3.acquired);
     }
 
@@ -324,22 +459,137 @@ mod tests {
         let ttl = Duration::from_secs(60);
 
         // Acquire lock
-        let acquire = lock_mgr.acquire(tenant, resource, holder, ttl).await.unwrap();
+        let acquire = lock_mgr.acquire(tenant, resource, holder, ttl, None).await.unwrap();
         assert!(acquire.acquired);
         let original_expiry = acquire.expires_at;
 
         // Wait a moment then renew
         tokio::time::sleep(Duration::from_millis(100)).await;
-        let renew = lock_mgr.renew(tenant, resource, holder, ttl).await.unwrap();
+        let renew = lock_mgr.renew(tenant, resource, holder, ttl, None).await.unwrap();
         assert!(renew.renewed);
         assert!(renew.expires_at >= original_expiry);
 
         // Cannot renew with wrong holder
-        let bad_renew = lock_mgr.renew(tenant, resource, "wrong-holder", ttl).await.unwrap();
+        let bad_renew = lock_mgr.renew(tenant, resource, "wrong-holder", ttl, None).await.unwrap();
         assert!(!bad_renew.renewed);
         assert_eq!(bad_renew.reason, "not_owner");
     }
 
+    #[tokio::test]
+    async fn test_renew_outlives_original_ttl() {
+        let (lock_mgr, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let resource = "index";
+
+        let acquire = lock_mgr
+            .acquire(tenant, resource, "holder-1", Duration::from_secs(1), None)
+            .await
+            .unwrap();
+        assert!(acquire.acquired);
+
+        let renew = lock_mgr
+            .renew(tenant, resource, "holder-1", Duration::from_secs(60), None)
+            .await
+            .unwrap();
+        assert!(renew.renewed);
+
+        // Past the original TTL, the renewed lease still holds
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let steal = lock_mgr
+            .acquire(tenant, resource, "holder-2", Duration::from_secs(60), None)
+            .await
+            .unwrap();
+        assert!(!steal.acquired);
+        assert_eq!(steal.current_holder, Some("holder-1".to_string()));
+
+        // A holder that lost its lease cannot renew it
+        lock_mgr.release(tenant, resource, "holder-1").await.unwrap();
+        let lost = lock_mgr
+            .renew(tenant, resource, "holder-1", Duration::from_secs(60), None)
+            .await
+            .unwrap();
+        assert!(!lost.renewed);
+        assert_eq!(lost.reason, "not_found");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dead_holder_is_taken_over() {
+        let (lock_mgr, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let ttl = Duration::from_secs(60);
+
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let this = HolderProcess::current().unwrap();
+        let dead = HolderProcess {
+            pid: child.id(),
+            ..this.clone()
+        };
+        child.wait().unwrap();
+
+        // Same host, dead process: taken over before the TTL runs out
+        lock_mgr.acquire(tenant, "dead", "crashed", ttl, Some(&dead)).await.unwrap();
+        let result = lock_mgr.acquire(tenant, "dead", "holder-2", ttl, None).await.unwrap();
+        assert!(result.acquired);
+
+        // Another host: only the TTL applies
+        let remote = HolderProcess {
+            hostname: "other-host".to_string(),
+            ..dead.clone()
+        };
+        lock_mgr.acquire(tenant, "remote", "crashed", ttl, Some(&remote)).await.unwrap();
+        let result = lock_mgr.acquire(tenant, "remote", "holder-2", ttl, None).await.unwrap();
+        assert!(!result.acquired);
+
+        // No process recorded: only the TTL applies
+        lock_mgr.acquire(tenant, "unknown", "holder-1", ttl, None).await.unwrap();
+        let result = lock_mgr.acquire(tenant, "unknown", "holder-2", ttl, None).await.unwrap();
+        assert!(!result.acquired);
+
+        // Renewing without a process keeps the recorded one
+        lock_mgr.acquire(tenant, "renewed", "crashed", ttl, Some(&dead)).await.unwrap();
+        lock_mgr.renew(tenant, "renewed", "crashed", ttl, None).await.unwrap();
+        let result = lock_mgr.acquire(tenant, "renewed", "holder-2", ttl, None).await.unwrap();
+        assert!(result.acquired);
+
+        // Live holder keeps its lock
+        lock_mgr.acquire(tenant, "live", "holder-1", ttl, Some(&this)).await.unwrap();
+        let result = lock_mgr.acquire(tenant, "live", "holder-2", ttl, None).await.unwrap();
+        assert!(!result.acquired);
+    }
+
+    #[tokio::test]
+    async fn test_list_locks() {
+        let (lock_mgr, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let ttl = Duration::from_secs(60);
+
+        assert!(lock_mgr.list_locks(tenant).await.unwrap().is_empty());
+
+        lock_mgr.acquire(tenant, "index", "holder-1", ttl, None).await.unwrap();
+        lock_mgr.acquire(tenant, "session-1", "holder-2", ttl, None).await.unwrap();
+        lock_mgr
+            .acquire(tenant, "stale", "holder-3", Duration::from_millis(1), None)
+            .await
+            .unwrap();
+        lock_mgr.acquire("other-tenant", "index", "holder-4", ttl, None).await.unwrap();
+
+        let locks = lock_mgr.list_locks(tenant).await.unwrap();
+        let summary: Vec<_> = locks
+            .iter()
+            .map(|l| (l.resource_id.as_str(), l.holder_id.as_str(), l.expired))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("index", "holder-1", false),
+                ("session-1", "holder-2", false),
+                ("stale", "holder-3", true),
+            ]
+        );
+        assert!(locks.iter().all(|l| l.acquired_at.is_some()));
+    }
+
     #[tokio::test]
     async fn test_release_not_owner() {
         let (lock_mgr, _temp) = setup().await;
@@ -348,7 +598,7 @@ mod tests {
         let ttl = Duration::from_secs(60);
 
         // holder-1 acquires
-        lock_mgr.acquire(tenant, resource, "holder-1", ttl).await.unwrap();
+        lock_mgr.acquire(tenant, resource, "holder-1", ttl, None).await.unwrap();
 
         // holder-2 tries to release
         let release = lock_mgr.release(tenant, resource, "holder-2").await.unwrap();
@@ -356,7 +606,7 @@ mod tests {
         assert_eq!(release.reason, "not_owner");
 
         // Lock should still be held by holder-1
-        let acquire = lock_mgr.acquire(tenant, resource, "holder-1", ttl).await.unwrap();
+        let acquire = lock_mgr.acquire(tenant, resource, "holder-1", ttl, None).await.unwrap();
         assert!(acquire.acquired); // Re-acquires (renews)
     }
 
@@ -366,10 +616,10 @@ mod tests {
         let ttl = Duration::from_secs(60);
 
         // tenant-a acquires
-        lock_mgr.acquire("tenant-a", "session-1", "holder", ttl).await.unwrap();
+        lock_mgr.acquire("tenant-a", "session-1", "holder", ttl, None).await.unwrap();
 
         // tenant-b can acquire same resource name (different tenant)
-        let result = lock_mgr.acquire("tenant-b", "session-1", "holder", ttl).await.unwrap();
+        let result = lock_mgr.acquire("tenant-b", "session-1", "holder", ttl, None).await.unwrap();
         assert!(result.acquired);
     }
 
@@ -381,7 +631,7 @@ mod tests {
 
         // Acquire with very short TTL
         let result = lock_mgr
-            .acquire(tenant, resource, "holder-1", Duration::from_millis(1))
+            .acquire(tenant, resource, "holder-1", Duration::from_millis(1), None)
             .await
             .unwrap();
         assert!(result.acquired);
@@ -391,7 +641,7 @@ mod tests {
 
         // Another holder can now acquire
         let result2 = lock_mgr
-            .acquire(tenant, resource, "holder-2", Duration::from_secs(60))
+            .acquire(tenant, resource, "holder-2", Duration::from_secs(60), None)
             .await
             .unwrap();
         assert!(result2.acquired);
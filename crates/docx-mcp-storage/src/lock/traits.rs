@@ -4,6 +4,45 @@ use async_trait::async_trait;
 
 use crate::error::StorageError;
 
+/// Resource id of the lock guarding a tenant's session index.
+///
+/// Clients hold it across a `LoadIndex`/`SaveIndex` round trip; the service
+/// takes it itself for operations that update the index server-side.
+pub const INDEX_LOCK_RESOURCE: &str = "index";
+
+/// The process on whose behalf a lock is held.
+///
+/// Recorded with the lock so that a lock whose process has died on the same
+/// host can be taken over before its TTL runs out. Locks acquired without
+/// it only expire through the TTL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HolderProcess {
+    pub pid: u32,
+    pub hostname: String,
+}
+
+impl HolderProcess {
+    /// The current process, if its hostname can be determined.
+    pub fn current() -> Option<Self> {
+        current_hostname().map(|hostname| Self {
+            pid: std::process::id(),
+            hostname,
+        })
+    }
+}
+
+#[cfg(unix)]
+fn current_hostname() -> Option<String> {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+}
+
+#[cfg(not(unix))]
+fn current_hostname() -> Option<String> {
+    None
+}
+
 /// Result of a lock acquisition attempt.
 #[derive(Debug, Clone)]
 pub struct LockAcquireResult {
@@ -35,6 +74,19 @@ pub struct LockRenewResult {
     pub reason: String,
 }
 
+/// A lock currently recorded by a lock manager.
+#[derive(Debug, Clone)]
+pub struct LockInfo {
+    pub resource_id: String,
+    pub holder_id: String,
+    /// When the holder first acquired the lock (Unix epoch seconds), if known.
+    pub acquired_at: Option<i64>,
+    /// Lock expiration timestamp (Unix epoch seconds).
+    pub expires_at: i64,
+    /// Whether the lock has expired without being cleaned up yet.
+    pub expired: bool,
+}
+
 /// Lock manager abstraction for tenant-aware distributed locking.
 ///
 /// Locks are on the pair `(tenant_id, resource_id)` to ensure tenant isolation.
@@ -51,6 +103,7 @@ pub trait LockManager: Send + Sync {
     /// * `resource_id` - Resource to lock (e.g., session_id)
     /// * `holder_id` - Unique identifier for this lock holder (UUID recommended)
     /// * `ttl` - Time-to-live for the lock to prevent orphaned locks
+    /// * `process` - Process of the holder, if known (see [`HolderProcess`])
     ///
     /// # Returns
     /// * `Ok(result)` - Lock result with acquisition status
@@ -60,6 +113,7 @@ pub trait LockManager: Send + Sync {
         resource_id: &str,
         holder_id: &str,
         ttl: Duration,
+        process: Option<&HolderProcess>,
     ) -> Result<LockAcquireResult, StorageError>;
 
     /// Release a lock.
@@ -75,11 +129,17 @@ pub trait LockManager: Send + Sync {
     /// Renew a lock's TTL.
     ///
     /// The lock is only renewed if `holder_id` matches the current holder.
+    /// Without `process`, the process recorded at acquisition is kept.
     async fn renew(
         &self,
         tenant_id: &str,
         resource_id: &str,
         holder_id: &str,
         ttl: Duration,
+        process: Option<&HolderProcess>,
     ) -> Result<LockRenewResult, StorageError>;
+
+    /// List the locks recorded for a tenant, including expired ones that
+    /// have not been cleaned up yet. Intended for debugging.
+    async fn list_locks(&self, tenant_id: &str) -> Result<Vec<LockInfo>, StorageError>;
 }
@@ -3,6 +3,7 @@ mod error;
 mod lock;
 mod service;
 mod storage;
+mod tls;
 
 use std::sync::Arc;
 
@@ -10,7 +11,7 @@ use clap::Parser;
 use tokio::net::UnixListener;
 use tokio::signal;
 use tonic::transport::Server;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 use config::{Config, StorageBackend, Transport};
@@ -33,13 +34,17 @@ async fn main() -> anyhow::Result<()> {
     info!("Starting docx-mcp-storage server");
     info!("  Transport: {}", config.transport);
     info!("  Backend: {}", config.storage_backend);
+    info!("  Chunk size: {} bytes", config.grpc_chunk_size);
+    info!("  Checkpoint threshold: {} WAL entries", config.checkpoint_threshold);
+    info!("  Max bundle size: {} bytes", config.max_bundle_size);
 
     // Create storage backend
     let storage: Arc<dyn crate::storage::StorageBackend> = match config.storage_backend {
         StorageBackend::Local => {
             let dir = config.effective_local_storage_dir();
             info!("  Local storage dir: {}", dir.display());
-            Arc::new(LocalStorage::new(&dir))
+            info!("  Durable writes: {}", config.durable_writes);
+            Arc::new(LocalStorage::new(&dir).with_durable_writes(config.durable_writes))
         }
         #[cfg(feature = "cloud")]
         StorageBackend::R2 => {
@@ -59,23 +64,40 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    info!("  Lock manager: {}", lock_manager.lock_type());
+
     // Create gRPC service
-    let service = StorageServiceImpl::new(storage, lock_manager);
+    let service = StorageServiceImpl::new(storage, lock_manager, config.grpc_chunk_size as usize)
+        .with_checkpoint_threshold(config.checkpoint_threshold)
+        .with_max_bundle_size(config.max_bundle_size as usize);
     let svc = StorageServiceServer::new(service);
 
     // Start server based on transport
     match config.transport {
         Transport::Tcp => {
             let addr = format!("{}:{}", config.host, config.port).parse()?;
+
+            let mut builder = Server::builder();
+            if let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) {
+                let client_ca = config.tls_client_ca.as_deref();
+                builder = builder.tls_config(tls::server_tls_config(cert, key, client_ca)?)?;
+                info!(
+                    "  TLS: enabled{}",
+                    if client_ca.is_some() { " (mutual)" } else { "" }
+                );
+            }
             info!("Listening on tcp://{}", addr);
 
-            Server::builder()
+            builder
                 .add_service(svc)
                 .serve_with_shutdown(addr, shutdown_signal())
                 .await?;
         }
         Transport::Unix => {
             let socket_path = config.effective_unix_socket();
+            if config.tls_cert.is_some() {
+                warn!("TLS settings are ignored with --transport unix");
+            }
 
             // Remove existing socket file if it exists
             if socket_path.exists() {
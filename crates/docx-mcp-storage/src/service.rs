@@ -2,13 +2,15 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
+use futures::TryStreamExt;
 use tokio::sync::mpsc;
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::{Request, Response, Status, Streaming};
-use tracing::{debug, instrument};
+use tracing::{debug, field, instrument, warn, Instrument, Span};
 
-use crate::lock::LockManager;
-use crate::storage::StorageBackend;
+use crate::config::DEFAULT_MAX_BUNDLE_SIZE;
+use crate::lock::{HolderProcess, LockManager, INDEX_LOCK_RESOURCE};
+use crate::storage::{checkpoint_recommended, validate_id, SessionListQuery, StorageBackend};
 
 // Include the generated protobuf code
 pub mod proto {
@@ -18,8 +20,12 @@ pub mod proto {
 use proto::storage_service_server::StorageService;
 use proto::*;
 
-/// Default chunk size for streaming: 256KB
-const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+/// Time-to-live of the locks the service takes for itself, so that they are
+/// freed even if the server dies while holding one.
+const SERVICE_LOCK_TTL: Duration = Duration::from_secs(30);
+
+/// How long the service waits for a lock held by someone else.
+const SERVICE_LOCK_WAIT: Duration = Duration::from_secs(10);
 
 /// Implementation of the StorageService gRPC service.
 pub struct StorageServiceImpl {
@@ -27,149 +33,470 @@ pub struct StorageServiceImpl {
     lock_manager: Arc<dyn LockManager>,
     version: String,
     chunk_size: usize,
+    checkpoint_threshold: u64,
+    max_bundle_size: usize,
+    /// This server's process, recorded on the locks it takes for itself.
+    process: Option<HolderProcess>,
 }
 
 impl StorageServiceImpl {
     pub fn new(
         storage: Arc<dyn StorageBackend>,
         lock_manager: Arc<dyn LockManager>,
+        chunk_size: usize,
     ) -> Self {
         Self {
             storage,
             lock_manager,
             version: env!("CARGO_PKG_VERSION").to_string(),
-            chunk_size: DEFAULT_CHUNK_SIZE,
+            chunk_size,
+            checkpoint_threshold: 0,
+            max_bundle_size: DEFAULT_MAX_BUNDLE_SIZE as usize,
+            process: HolderProcess::current(),
         }
     }
 
+    /// Recommend a checkpoint from `append_wal` once this many WAL entries
+    /// accumulated since the last one recorded in the index (0 = never).
+    pub fn with_checkpoint_threshold(mut self, threshold: u64) -> Self {
+        self.checkpoint_threshold = threshold;
+        self
+    }
+
+    /// Reject `import_bundle` uploads larger than this many bytes.
+    pub fn with_max_bundle_size(mut self, max_bundle_size: usize) -> Self {
+        self.max_bundle_size = max_bundle_size;
+        self
+    }
+
     /// Extract tenant_id from request, returning error if missing.
+    ///
+    /// The tenant id is recorded on the current RPC span.
+    #[allow(clippy::result_large_err)]
     fn get_tenant_id(context: Option<&TenantContext>) -> Result<&str, Status> {
-        context
+        let tenant_id = context
             .map(|c| c.tenant_id.as_str())
             .filter(|id| !id.is_empty())
             .ok_or_else(|| Status::invalid_argument("tenant_id is required"))
+            .and_then(Self::validate_tenant_id)?;
+        Span::current().record("tenant_id", tenant_id);
+        Ok(tenant_id)
     }
 
-    /// Split data into chunks for streaming.
-    fn chunk_data(&self, data: Vec<u8>) -> Vec<Vec<u8>> {
-        data.chunks(self.chunk_size)
-            .map(|c| c.to_vec())
-            .collect()
+    /// The holder process described by a lock request, if it names one.
+    fn holder_process(pid: u32, hostname: String) -> Option<HolderProcess> {
+        (pid != 0 && !hostname.is_empty()).then_some(HolderProcess { pid, hostname })
+    }
+
+    /// Record the session id on the current RPC span, so that nested logs,
+    /// including those of spawned streaming tasks, carry it.
+    fn record_session_id(session_id: &str) {
+        if !session_id.is_empty() {
+            Span::current().record("session_id", session_id);
+        }
+    }
+
+    /// Take the lock on `resource_id` for an operation of the service itself,
+    /// retrying with backoff for up to [`SERVICE_LOCK_WAIT`] while someone
+    /// else holds it. Returns the holder id to pass to [`Self::unlock`].
+    #[allow(clippy::result_large_err)]
+    async fn lock(&self, tenant_id: &str, resource_id: &str) -> Result<String, Status> {
+        let holder_id = format!("docx-storage-{}", uuid::Uuid::new_v4());
+        let deadline = tokio::time::Instant::now() + SERVICE_LOCK_WAIT;
+        let mut delay = Duration::from_millis(10);
+
+        loop {
+            let result = self
+                .lock_manager
+                .acquire(
+                    tenant_id,
+                    resource_id,
+                    &holder_id,
+                    SERVICE_LOCK_TTL,
+                    self.process.as_ref(),
+                )
+                .await
+                .map_err(Status::from)?;
+            if result.acquired {
+                return Ok(holder_id);
+            }
+            if tokio::time::Instant::now() + delay > deadline {
+                return Err(Status::unavailable(format!(
+                    "{} of tenant {} is locked by {}",
+                    resource_id,
+                    tenant_id,
+                    result.current_holder.unwrap_or_default()
+                )));
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(Duration::from_millis(500));
+        }
+    }
+
+    /// Release a lock taken with [`Self::lock`]. Failures are only logged,
+    /// the lock expires on its own.
+    async fn unlock(&self, tenant_id: &str, resource_id: &str, holder_id: &str) {
+        match self
+            .lock_manager
+            .release(tenant_id, resource_id, holder_id)
+            .await
+        {
+            Ok(result) if !result.released => {
+                warn!("Lock on {} was not released: {}", resource_id, result.reason)
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to release lock on {}: {}", resource_id, e),
+        }
+    }
+
+    /// Reject tenant ids that are not plain identifiers, see [`validate_id`].
+    #[allow(clippy::result_large_err)]
+    fn validate_tenant_id(tenant_id: &str) -> Result<&str, Status> {
+        validate_id("tenant_id", tenant_id)?;
+        Ok(tenant_id)
     }
 }
 
 type StreamResult<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
 
-#[tonic::async_trait]
-impl StorageService for StorageServiceImpl {
-    type LoadSessionStream = StreamResult<DataChunk>;
-    type LoadCheckpointStream = StreamResult<LoadCheckpointChunk>;
+/// Checks the optional `chunk_index` numbering of an upload.
+///
+/// An upload whose first chunk has index 0 is unnumbered and must stay so;
+/// otherwise chunks must count up from 1 without gaps or repeats.
+#[derive(Debug, Default)]
+struct ChunkSequence {
+    received: u32,
+    numbered: bool,
+}
 
-    // =========================================================================
-    // Session Operations (Streaming)
-    // =========================================================================
+impl ChunkSequence {
+    /// Record the next chunk, failing with `DATA_LOSS` if it is out of order.
+    #[allow(clippy::result_large_err)]
+    fn next(&mut self, chunk_index: u32) -> Result<(), Status> {
+        if self.received == 0 {
+            self.numbered = chunk_index != 0;
+        }
+        self.received += 1;
+
+        let expected = if self.numbered { self.received } else { 0 };
+        if chunk_index != expected {
+            return Err(Status::data_loss(format!(
+                "expected chunk_index {} but received {}",
+                expected, chunk_index
+            )));
+        }
+        Ok(())
+    }
+}
 
-    #[instrument(skip(self, request), level = "debug")]
-    async fn load_session(
+impl StorageServiceImpl {
+    /// Accumulate a `SaveSession` upload and save it once complete.
+    ///
+    /// Nothing is written unless the stream ends with an `is_last` chunk, the
+    /// chunks are in order (see [`ChunkSequence`]) and, when the client
+    /// declared a `total_size`, the received length matches.
+    async fn save_session_stream<S>(
         &self,
-        request: Request<LoadSessionRequest>,
-    ) -> Result<Response<Self::LoadSessionStream>, Status> {
-        let req = request.into_inner();
-        let tenant_id = Self::get_tenant_id(req.context.as_ref())?.to_string();
-        let session_id = req.session_id.clone();
+        mut stream: S,
+    ) -> Result<Response<SaveSessionResponse>, Status>
+    where
+        S: Stream<Item = Result<SaveSessionChunk, Status>> + Unpin,
+    {
+        let mut tenant_id: Option<String> = None;
+        let mut session_id: Option<String> = None;
+        let mut total_size = 0;
+        let mut data = Vec::new();
+        let mut sequence = ChunkSequence::default();
+        let mut complete = false;
 
-        let result = self
-            .storage
-            .load_session(&tenant_id, &session_id)
-            .await
-            .map_err(Status::from)?;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            sequence.next(chunk.chunk_index)?;
 
-        let (tx, rx) = mpsc::channel(4);
-        let chunk_size = self.chunk_size;
+            // Extract metadata from first chunk
+            if tenant_id.is_none() {
+                tenant_id = chunk.context.map(|c| c.tenant_id);
+                session_id = Some(chunk.session_id);
+                total_size = chunk.total_size;
+            }
 
-        tokio::spawn(async move {
-            match result {
-                Some(data) => {
-                    let total_size = data.len() as u64;
-                    let chunks: Vec<Vec<u8>> = data.chunks(chunk_size).map(|c| c.to_vec()).collect();
-                    let total_chunks = chunks.len();
-
-                    for (i, chunk) in chunks.into_iter().enumerate() {
-                        let is_first = i == 0;
-                        let is_last = i == total_chunks - 1;
-
-                        let msg = DataChunk {
-                            data: chunk,
-                            is_last,
-                            found: is_first, // Only meaningful in first chunk
-                            total_size: if is_first { total_size } else { 0 },
-                        };
-
-                        if tx.send(Ok(msg)).await.is_err() {
-                            break; // Client disconnected
-                        }
-                    }
-                }
-                None => {
-                    // Send a single chunk indicating not found
-                    let _ = tx.send(Ok(DataChunk {
-                        data: vec![],
-                        is_last: true,
-                        found: false,
-                        total_size: 0,
-                    })).await;
-                }
+            data.extend(chunk.data);
+
+            if chunk.is_last {
+                complete = true;
+                break;
             }
-        });
+        }
 
-        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+        if !complete {
+            return Err(Status::data_loss("upload ended without a final chunk"));
+        }
+
+        let tenant_id = tenant_id
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| Status::invalid_argument("tenant_id is required in first chunk"))?;
+        Self::validate_tenant_id(&tenant_id)?;
+        let session_id = session_id
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| Status::invalid_argument("session_id is required in first chunk"))?;
+        Span::current().record("tenant_id", tenant_id.as_str());
+        Self::record_session_id(&session_id);
+
+        if total_size != 0 && total_size != data.len() as u64 {
+            return Err(Status::data_loss(format!(
+                "received {} bytes but {} were declared",
+                data.len(),
+                total_size
+            )));
+        }
+
+        debug!("Saving session {} for tenant {} ({} bytes)", session_id, tenant_id, data.len());
+
+        self.storage
+            .save_session(&tenant_id, &session_id, &data)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SaveSessionResponse { success: true }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
-    async fn save_session(
+    /// Accumulate a `SaveCheckpoint` upload and save it once complete, with
+    /// the same completeness checks as [`Self::save_session_stream`].
+    async fn save_checkpoint_stream<S>(
         &self,
-        request: Request<Streaming<SaveSessionChunk>>,
-    ) -> Result<Response<SaveSessionResponse>, Status> {
-        let mut stream = request.into_inner();
-
+        mut stream: S,
+    ) -> Result<Response<SaveCheckpointResponse>, Status>
+    where
+        S: Stream<Item = Result<SaveCheckpointChunk, Status>> + Unpin,
+    {
         let mut tenant_id: Option<String> = None;
         let mut session_id: Option<String> = None;
+        let mut position: u64 = 0;
         let mut data = Vec::new();
+        let mut sequence = ChunkSequence::default();
+        let mut complete = false;
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
+            sequence.next(chunk.chunk_index)?;
 
             // Extract metadata from first chunk
             if tenant_id.is_none() {
                 tenant_id = chunk.context.map(|c| c.tenant_id);
                 session_id = Some(chunk.session_id);
+                position = chunk.position;
             }
 
             data.extend(chunk.data);
 
             if chunk.is_last {
+                complete = true;
                 break;
             }
         }
 
+        if !complete {
+            return Err(Status::data_loss("upload ended without a final chunk"));
+        }
+
         let tenant_id = tenant_id
             .filter(|s| !s.is_empty())
             .ok_or_else(|| Status::invalid_argument("tenant_id is required in first chunk"))?;
+        Self::validate_tenant_id(&tenant_id)?;
         let session_id = session_id
             .filter(|s| !s.is_empty())
             .ok_or_else(|| Status::invalid_argument("session_id is required in first chunk"))?;
+        Span::current().record("tenant_id", tenant_id.as_str());
+        Self::record_session_id(&session_id);
 
-        debug!("Saving session {} for tenant {} ({} bytes)", session_id, tenant_id, data.len());
+        debug!(
+            "Saving checkpoint at position {} for session {} tenant {} ({} bytes)",
+            position, session_id, tenant_id, data.len()
+        );
 
         self.storage
-            .save_session(&tenant_id, &session_id, &data)
+            .save_checkpoint(&tenant_id, &session_id, position, &data)
             .await
             .map_err(Status::from)?;
 
-        Ok(Response::new(SaveSessionResponse { success: true }))
+        Ok(Response::new(SaveCheckpointResponse { success: true }))
+    }
+
+    /// Accumulate an `ImportBundle` upload and import it once complete.
+    ///
+    /// The bundle is buffered in memory, so uploads larger than
+    /// `max_bundle_size` are rejected as soon as they cross the limit. The
+    /// upload must be complete and in order, as for `SaveSession`.
+    async fn import_bundle_stream<S>(
+        &self,
+        mut stream: S,
+    ) -> Result<Response<ImportBundleResponse>, Status>
+    where
+        S: Stream<Item = Result<ImportBundleChunk, Status>> + Unpin,
+    {
+        let mut tenant_id: Option<String> = None;
+        let mut data = Vec::new();
+        let mut sequence = ChunkSequence::default();
+        let mut complete = false;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            sequence.next(chunk.chunk_index)?;
+
+            // Extract metadata from first chunk
+            if tenant_id.is_none() {
+                tenant_id = chunk.context.map(|c| c.tenant_id);
+            }
+
+            if data.len() + chunk.data.len() > self.max_bundle_size {
+                return Err(Status::resource_exhausted(format!(
+                    "bundle exceeds the {} byte limit",
+                    self.max_bundle_size
+                )));
+            }
+            data.extend(chunk.data);
+
+            if chunk.is_last {
+                complete = true;
+                break;
+            }
+        }
+
+        if !complete {
+            return Err(Status::data_loss("upload ended without a final chunk"));
+        }
+
+        let tenant_id = tenant_id
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| Status::invalid_argument("tenant_id is required in first chunk"))?;
+        Self::validate_tenant_id(&tenant_id)?;
+        Span::current().record("tenant_id", tenant_id.as_str());
+
+        debug!("Importing bundle for tenant {} ({} bytes)", tenant_id, data.len());
+
+        let holder_id = self.lock(&tenant_id, INDEX_LOCK_RESOURCE).await?;
+        let result = self.storage.import_bundle(&tenant_id, &data).await;
+        self.unlock(&tenant_id, INDEX_LOCK_RESOURCE, &holder_id).await;
+        let session_id = result.map_err(Status::from)?;
+        Self::record_session_id(&session_id);
+
+        Ok(Response::new(ImportBundleResponse { session_id }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    /// Stream a blob as `DataChunk`s, or a single "not found" chunk if absent.
+    fn stream_data_chunks(&self, result: Option<Vec<u8>>) -> StreamResult<DataChunk> {
+        let (tx, rx) = mpsc::channel(4);
+        let chunk_size = self.chunk_size;
+
+        tokio::spawn(
+            async move {
+                match result {
+                    Some(data) => {
+                        let total_size = data.len() as u64;
+                        let chunks: Vec<Vec<u8>> =
+                            data.chunks(chunk_size).map(|c| c.to_vec()).collect();
+                        let total_chunks = chunks.len();
+
+                        for (i, chunk) in chunks.into_iter().enumerate() {
+                            let is_first = i == 0;
+                            let is_last = i == total_chunks - 1;
+
+                            let msg = DataChunk {
+                                data: chunk,
+                                is_last,
+                                found: is_first, // Only meaningful in first chunk
+                                total_size: if is_first { total_size } else { 0 },
+                            };
+
+                            if tx.send(Ok(msg)).await.is_err() {
+                                debug!(
+                                    "Client disconnected after {} of {} chunks",
+                                    i, total_chunks
+                                );
+                                return;
+                            }
+                        }
+                        debug!("Streamed {} bytes in {} chunks", total_size, total_chunks);
+                    }
+                    None => {
+                        // Send a single chunk indicating not found
+                        let _ = tx.send(Ok(DataChunk {
+                            data: vec![],
+                            is_last: true,
+                            found: false,
+                            total_size: 0,
+                        })).await;
+                    }
+                }
+            }
+            .instrument(Span::current()),
+        );
+
+        Box::pin(ReceiverStream::new(rx))
+    }
+}
+
+fn wal_entry_to_proto(entry: crate::storage::WalEntry) -> WalEntry {
+    WalEntry {
+        position: entry.position,
+        operation: entry.operation,
+        path: entry.path,
+        patch_json: entry.patch_json,
+        timestamp_unix: entry.timestamp.timestamp(),
+    }
+}
+
+#[tonic::async_trait]
+impl StorageService for StorageServiceImpl {
+    type LoadSessionStream = StreamResult<DataChunk>;
+    type LoadCheckpointStream = StreamResult<LoadCheckpointChunk>;
+    type ExportBundleStream = StreamResult<DataChunk>;
+    type StreamWalStream = StreamResult<WalEntry>;
+
+    // =========================================================================
+    // Session Operations (Streaming)
+    // =========================================================================
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn load_session(
+        &self,
+        request: Request<LoadSessionRequest>,
+    ) -> Result<Response<Self::LoadSessionStream>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?.to_string();
+        Self::record_session_id(&req.session_id);
+        let session_id = req.session_id.clone();
+
+        let result = self
+            .storage
+            .load_session(&tenant_id, &session_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(self.stream_data_chunks(result)))
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn save_session(
+        &self,
+        request: Request<Streaming<SaveSessionChunk>>,
+    ) -> Result<Response<SaveSessionResponse>, Status> {
+        self.save_session_stream(request.into_inner()).await
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn list_sessions(
         &self,
         request: Request<ListSessionsRequest>,
@@ -177,13 +504,23 @@ impl StorageService for StorageServiceImpl {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
 
-        let sessions = self
+        let query = SessionListQuery {
+            offset: req.offset as usize,
+            limit: (req.limit > 0).then_some(req.limit as usize),
+            source_path_prefix: Some(req.source_path_prefix).filter(|p| !p.is_empty()),
+            modified_after: (req.modified_after_unix != 0)
+                .then(|| chrono::DateTime::from_timestamp(req.modified_after_unix, 0))
+                .flatten(),
+        };
+
+        let page = self
             .storage
-            .list_sessions(tenant_id)
+            .list_sessions_page(tenant_id, &query)
             .await
             .map_err(Status::from)?;
 
-        let sessions = sessions
+        let sessions = page
+            .sessions
             .into_iter()
             .map(|s| SessionInfo {
                 session_id: s.session_id,
@@ -194,16 +531,24 @@ impl StorageService for StorageServiceImpl {
             })
             .collect();
 
-        Ok(Response::new(ListSessionsResponse { sessions }))
+        Ok(Response::new(ListSessionsResponse {
+            sessions,
+            total: page.total as u64,
+        }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn delete_session(
         &self,
         request: Request<DeleteSessionRequest>,
     ) -> Result<Response<DeleteSessionResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let existed = self
             .storage
@@ -217,13 +562,42 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn delete_tenant(
+        &self,
+        request: Request<DeleteTenantRequest>,
+    ) -> Result<Response<DeleteTenantResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+
+        let existed = self
+            .storage
+            .delete_tenant(tenant_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteTenantResponse {
+            success: true,
+            existed,
+        }))
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn session_exists(
         &self,
         request: Request<SessionExistsRequest>,
     ) -> Result<Response<SessionExistsResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let exists = self
             .storage
@@ -234,11 +608,43 @@ impl StorageService for StorageServiceImpl {
         Ok(Response::new(SessionExistsResponse { exists }))
     }
 
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn fork_session(
+        &self,
+        request: Request<ForkSessionRequest>,
+    ) -> Result<Response<ForkSessionResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        if req.new_session_id.is_empty() {
+            return Err(Status::invalid_argument("new_session_id is required"));
+        }
+
+        let holder_id = self.lock(tenant_id, INDEX_LOCK_RESOURCE).await?;
+        let result = self
+            .storage
+            .fork_session(tenant_id, &req.session_id, &req.new_session_id)
+            .await;
+        self.unlock(tenant_id, INDEX_LOCK_RESOURCE, &holder_id).await;
+        result.map_err(Status::from)?;
+
+        Ok(Response::new(ForkSessionResponse { success: true }))
+    }
+
     // =========================================================================
     // Index Operations
     // =========================================================================
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn load_index(
         &self,
         request: Request<LoadIndexRequest>,
@@ -264,7 +670,11 @@ impl StorageService for StorageServiceImpl {
         Ok(Response::new(LoadIndexResponse { index_json, found }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn save_index(
         &self,
         request: Request<SaveIndexRequest>,
@@ -272,7 +682,7 @@ impl StorageService for StorageServiceImpl {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
 
-        let index: crate::storage::SessionIndex = serde_json::from_slice(&req.index_json)
+        let (index, _) = crate::storage::SessionIndex::from_json(&req.index_json)
             .map_err(|e| Status::invalid_argument(format!("Invalid index JSON: {}", e)))?;
 
         self.storage
@@ -287,13 +697,22 @@ impl StorageService for StorageServiceImpl {
     // WAL Operations
     // =========================================================================
 
-    #[instrument(skip(self, request), level = "debug", fields(entries_count = request.get_ref().entries.len()))]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(
+            tenant_id = field::Empty,
+            session_id = field::Empty,
+            entries_count = request.get_ref().entries.len()
+        )
+    )]
     async fn append_wal(
         &self,
         request: Request<AppendWalRequest>,
     ) -> Result<Response<AppendWalResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let entries: Vec<crate::storage::WalEntry> = req
             .entries
@@ -314,19 +733,44 @@ impl StorageService for StorageServiceImpl {
             .await
             .map_err(Status::from)?;
 
+        // The index records the checkpoints, which keeps directory scans off
+        // the write path. The append already succeeded: failing to read the
+        // index only loses the recommendation.
+        let checkpoint_recommended = self.checkpoint_threshold > 0 && {
+            match self.storage.load_index(tenant_id).await {
+                Ok(index) => {
+                    let last_checkpoint = index
+                        .as_ref()
+                        .and_then(|index| index.sessions.get(&req.session_id))
+                        .and_then(|entry| entry.checkpoint_positions.iter().max().copied());
+                    checkpoint_recommended(new_position, last_checkpoint, self.checkpoint_threshold)
+                }
+                Err(e) => {
+                    warn!("Failed to load index for checkpoint recommendation: {}", e);
+                    false
+                }
+            }
+        };
+
         Ok(Response::new(AppendWalResponse {
             success: true,
             new_position,
+            checkpoint_recommended,
         }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn read_wal(
         &self,
         request: Request<ReadWalRequest>,
     ) -> Result<Response<ReadWalResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let limit = if req.limit > 0 { Some(req.limit) } else { None };
 
@@ -336,27 +780,88 @@ impl StorageService for StorageServiceImpl {
             .await
             .map_err(Status::from)?;
 
+        let entries = entries.into_iter().map(wal_entry_to_proto).collect();
+
+        Ok(Response::new(ReadWalResponse { entries, has_more }))
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn stream_wal(
+        &self,
+        request: Request<ReadWalRequest>,
+    ) -> Result<Response<Self::StreamWalStream>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        let limit = if req.limit > 0 { Some(req.limit) } else { None };
+
+        let entries = self
+            .storage
+            .stream_wal(tenant_id, &req.session_id, req.from_position, limit)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(Box::pin(entries.map_ok(wal_entry_to_proto).map_err(Status::from))))
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn read_wal_patches(
+        &self,
+        request: Request<ReadWalRequest>,
+    ) -> Result<Response<ReadWalPatchesResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        let limit = if req.limit > 0 { Some(req.limit) } else { None };
+
+        let (entries, has_more) = self
+            .storage
+            .read_wal(tenant_id, &req.session_id, req.from_position, limit)
+            .await
+            .map_err(Status::from)?;
+
+        // One entry without a JSON Patch form must not hide the others
         let entries = entries
             .into_iter()
-            .map(|e| WalEntry {
-                position: e.position,
-                operation: e.operation,
-                path: e.path,
-                patch_json: e.patch_json,
-                timestamp_unix: e.timestamp.timestamp(),
+            .map(|e| match e.as_json_patch() {
+                Ok(patch) => WalPatch {
+                    position: e.position,
+                    json_patch: serde_json::Value::Array(patch).to_string(),
+                    error: String::new(),
+                },
+                Err(err) => WalPatch {
+                    position: e.position,
+                    json_patch: String::new(),
+                    error: err.to_string(),
+                },
             })
             .collect();
 
-        Ok(Response::new(ReadWalResponse { entries, has_more }))
+        Ok(Response::new(ReadWalPatchesResponse { entries, has_more }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn truncate_wal(
         &self,
         request: Request<TruncateWalRequest>,
     ) -> Result<Response<TruncateWalResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let entries_removed = self
             .storage
@@ -370,66 +875,69 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn repair_wal(
+        &self,
+        request: Request<RepairWalRequest>,
+    ) -> Result<Response<RepairWalResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        validate_id("session_id", &req.session_id)?;
+
+        // The session lock keeps appends out while the WAL is rewritten; it
+        // is always taken before the index lock
+        let session_holder = self.lock(tenant_id, &req.session_id).await?;
+        let result = match self.lock(tenant_id, INDEX_LOCK_RESOURCE).await {
+            Ok(index_holder) => {
+                let result = self.storage.repair_wal(tenant_id, &req.session_id).await;
+                self.unlock(tenant_id, INDEX_LOCK_RESOURCE, &index_holder).await;
+                result.map_err(Status::from)
+            }
+            Err(status) => Err(status),
+        };
+        self.unlock(tenant_id, &req.session_id, &session_holder).await;
+        let report = result?;
+
+        Ok(Response::new(RepairWalResponse {
+            entries_recovered: report.recovered,
+            lines_dropped: report.dropped,
+        }))
+    }
+
     // =========================================================================
     // Checkpoint Operations (Streaming)
     // =========================================================================
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn save_checkpoint(
         &self,
         request: Request<Streaming<SaveCheckpointChunk>>,
     ) -> Result<Response<SaveCheckpointResponse>, Status> {
-        let mut stream = request.into_inner();
-
-        let mut tenant_id: Option<String> = None;
-        let mut session_id: Option<String> = None;
-        let mut position: u64 = 0;
-        let mut data = Vec::new();
-
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-
-            // Extract metadata from first chunk
-            if tenant_id.is_none() {
-                tenant_id = chunk.context.map(|c| c.tenant_id);
-                session_id = Some(chunk.session_id);
-                position = chunk.position;
-            }
-
-            data.extend(chunk.data);
-
-            if chunk.is_last {
-                break;
-            }
-        }
-
-        let tenant_id = tenant_id
-            .filter(|s| !s.is_empty())
-            .ok_or_else(|| Status::invalid_argument("tenant_id is required in first chunk"))?;
-        let session_id = session_id
-            .filter(|s| !s.is_empty())
-            .ok_or_else(|| Status::invalid_argument("session_id is required in first chunk"))?;
-
-        debug!(
-            "Saving checkpoint at position {} for session {} tenant {} ({} bytes)",
-            position, session_id, tenant_id, data.len()
-        );
-
-        self.storage
-            .save_checkpoint(&tenant_id, &session_id, position, &data)
-            .await
-            .map_err(Status::from)?;
-
-        Ok(Response::new(SaveCheckpointResponse { success: true }))
+        self.save_checkpoint_stream(request.into_inner()).await
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn load_checkpoint(
         &self,
         request: Request<LoadCheckpointRequest>,
     ) -> Result<Response<Self::LoadCheckpointStream>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?.to_string();
+        Self::record_session_id(&req.session_id);
         let session_id = req.session_id.clone();
         let position = req.position;
 
@@ -442,53 +950,67 @@ impl StorageService for StorageServiceImpl {
         let (tx, rx) = mpsc::channel(4);
         let chunk_size = self.chunk_size;
 
-        tokio::spawn(async move {
-            match result {
-                Some((data, actual_position)) => {
-                    let total_size = data.len() as u64;
-                    let chunks: Vec<Vec<u8>> = data.chunks(chunk_size).map(|c| c.to_vec()).collect();
-                    let total_chunks = chunks.len();
-
-                    for (i, chunk) in chunks.into_iter().enumerate() {
-                        let is_first = i == 0;
-                        let is_last = i == total_chunks - 1;
-
-                        let msg = LoadCheckpointChunk {
-                            data: chunk,
-                            is_last,
-                            found: is_first, // Only meaningful in first chunk
-                            position: if is_first { actual_position } else { 0 },
-                            total_size: if is_first { total_size } else { 0 },
-                        };
-
-                        if tx.send(Ok(msg)).await.is_err() {
-                            break; // Client disconnected
+        tokio::spawn(
+            async move {
+                match result {
+                    Some((data, actual_position)) => {
+                        let total_size = data.len() as u64;
+                        let chunks: Vec<Vec<u8>> =
+                            data.chunks(chunk_size).map(|c| c.to_vec()).collect();
+                        let total_chunks = chunks.len();
+
+                        for (i, chunk) in chunks.into_iter().enumerate() {
+                            let is_first = i == 0;
+                            let is_last = i == total_chunks - 1;
+
+                            let msg = LoadCheckpointChunk {
+                                data: chunk,
+                                is_last,
+                                found: is_first, // Only meaningful in first chunk
+                                position: if is_first { actual_position } else { 0 },
+                                total_size: if is_first { total_size } else { 0 },
+                            };
+
+                            if tx.send(Ok(msg)).await.is_err() {
+                                debug!(
+                                    "Client disconnected after {} of {} chunks",
+                                    i, total_chunks
+                                );
+                                return;
+                            }
                         }
+                        debug!("Streamed {} bytes in {} chunks", total_size, total_chunks);
+                    }
+                    None => {
+                        // Send a single chunk indicating not found
+                        let _ = tx.send(Ok(LoadCheckpointChunk {
+                            data: vec![],
+                            is_last: true,
+                            found: false,
+                            position: 0,
+                            total_size: 0,
+                        })).await;
                     }
-                }
-                None => {
-                    // Send a single chunk indicating not found
-                    let _ = tx.send(Ok(LoadCheckpointChunk {
-                        data: vec![],
-                        is_last: true,
-                        found: false,
-                        position: 0,
-                        total_size: 0,
-                    })).await;
                 }
             }
-        });
+            .instrument(Span::current()),
+        );
 
         Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn list_checkpoints(
         &self,
         request: Request<ListCheckpointsRequest>,
     ) -> Result<Response<ListCheckpointsResponse>, Status> {
         let req = request.into_inner();
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
 
         let checkpoints = self
             .storage
@@ -508,11 +1030,109 @@ impl StorageService for StorageServiceImpl {
         Ok(Response::new(ListCheckpointsResponse { checkpoints }))
     }
 
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn gc_checkpoints(
+        &self,
+        request: Request<GcCheckpointsRequest>,
+    ) -> Result<Response<GcCheckpointsResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        let keep_every = if req.keep_every > 0 { Some(req.keep_every) } else { None };
+
+        let holder_id = self.lock(tenant_id, INDEX_LOCK_RESOURCE).await?;
+        let result = self
+            .storage
+            .gc_checkpoints(tenant_id, &req.session_id, req.keep_latest as usize, keep_every)
+            .await;
+        self.unlock(tenant_id, INDEX_LOCK_RESOURCE, &holder_id).await;
+        let removed_positions = result.map_err(Status::from)?;
+
+        Ok(Response::new(GcCheckpointsResponse { removed_positions }))
+    }
+
+    // =========================================================================
+    // Bundle Operations (Streaming)
+    // =========================================================================
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn export_bundle(
+        &self,
+        request: Request<ExportBundleRequest>,
+    ) -> Result<Response<Self::ExportBundleStream>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+        Self::record_session_id(&req.session_id);
+
+        let bundle = self
+            .storage
+            .export_bundle(tenant_id, &req.session_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(self.stream_data_chunks(Some(bundle))))
+    }
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn import_bundle(
+        &self,
+        request: Request<Streaming<ImportBundleChunk>>,
+    ) -> Result<Response<ImportBundleResponse>, Status> {
+        self.import_bundle_stream(request.into_inner()).await
+    }
+
+    // =========================================================================
+    // Usage Operations
+    // =========================================================================
+
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn get_storage_usage(
+        &self,
+        request: Request<GetStorageUsageRequest>,
+    ) -> Result<Response<GetStorageUsageResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+
+        let usage = self
+            .storage
+            .storage_usage(tenant_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetStorageUsageResponse {
+            sessions_bytes: usage.sessions_bytes,
+            wal_bytes: usage.wal_bytes,
+            checkpoint_bytes: usage.checkpoint_bytes,
+            session_count: usage.count,
+        }))
+    }
+
     // =========================================================================
     // Lock Operations
     // =========================================================================
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn acquire_lock(
         &self,
         request: Request<AcquireLockRequest>,
@@ -521,10 +1141,11 @@ impl StorageService for StorageServiceImpl {
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
 
         let ttl = Duration::from_secs(req.ttl_seconds.max(1) as u64);
+        let process = Self::holder_process(req.holder_pid, req.holder_hostname);
 
         let result = self
             .lock_manager
-            .acquire(tenant_id, &req.resource_id, &req.holder_id, ttl)
+            .acquire(tenant_id, &req.resource_id, &req.holder_id, ttl, process.as_ref())
             .await
             .map_err(Status::from)?;
 
@@ -535,7 +1156,11 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn release_lock(
         &self,
         request: Request<ReleaseLockRequest>,
@@ -555,7 +1180,11 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 
-    #[instrument(skip(self, request), level = "debug")]
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
     async fn renew_lock(
         &self,
         request: Request<RenewLockRequest>,
@@ -564,10 +1193,11 @@ impl StorageService for StorageServiceImpl {
         let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
 
         let ttl = Duration::from_secs(req.ttl_seconds.max(1) as u64);
+        let process = Self::holder_process(req.holder_pid, req.holder_hostname);
 
         let result = self
             .lock_manager
-            .renew(tenant_id, &req.resource_id, &req.holder_id, ttl)
+            .renew(tenant_id, &req.resource_id, &req.holder_id, ttl, process.as_ref())
             .await
             .map_err(Status::from)?;
 
@@ -578,11 +1208,41 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 
+    #[instrument(
+        skip(self, request),
+        level = "info",
+        fields(tenant_id = field::Empty, session_id = field::Empty)
+    )]
+    async fn list_locks(
+        &self,
+        request: Request<ListLocksRequest>,
+    ) -> Result<Response<ListLocksResponse>, Status> {
+        let req = request.into_inner();
+        let tenant_id = Self::get_tenant_id(req.context.as_ref())?;
+
+        let locks = self
+            .lock_manager
+            .list_locks(tenant_id)
+            .await
+            .map_err(Status::from)?
+            .into_iter()
+            .map(|lock| LockInfo {
+                resource_id: lock.resource_id,
+                holder_id: lock.holder_id,
+                acquired_at_unix: lock.acquired_at.unwrap_or_default(),
+                expires_at_unix: lock.expires_at,
+                expired: lock.expired,
+            })
+            .collect();
+
+        Ok(Response::new(ListLocksResponse { locks }))
+    }
+
     // =========================================================================
     // Health Check
     // =========================================================================
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(skip(self), level = "info")]
     async fn health_check(
         &self,
         _request: Request<HealthCheckRequest>,
@@ -595,3 +1255,437 @@ impl StorageService for StorageServiceImpl {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock::FileLock;
+    use crate::storage::{LocalStorage, SessionIndex, SessionIndexEntry};
+    use tempfile::TempDir;
+    use tracing_subscriber::filter::filter_fn;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn setup(chunk_size: usize) -> (StorageServiceImpl, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let service = StorageServiceImpl::new(
+            Arc::new(LocalStorage::new(temp_dir.path())),
+            Arc::new(FileLock::new(temp_dir.path())),
+            chunk_size,
+        );
+        (service, temp_dir)
+    }
+
+    fn context(tenant: &str) -> Option<TenantContext> {
+        Some(TenantContext {
+            tenant_id: tenant.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_load_session_uses_chunk_size() {
+        let (service, _temp) = setup(64 * 1024);
+        let data = vec![7u8; 1024 * 1024];
+        service
+            .storage
+            .save_session("tenant", "session", &data)
+            .await
+            .unwrap();
+
+        let response = service
+            .load_session(Request::new(LoadSessionRequest {
+                context: context("tenant"),
+                session_id: "session".to_string(),
+            }))
+            .await
+            .unwrap();
+        let chunks: Vec<DataChunk> = response
+            .into_inner()
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks.len(), 16);
+        assert!(chunks.iter().all(|c| c.data.len() == 64 * 1024));
+        assert!(chunks[0].found);
+        assert_eq!(chunks[0].total_size, data.len() as u64);
+        assert!(chunks[15].is_last);
+    }
+
+    fn upload_chunk(data: &[u8], total_size: u64, is_last: bool) -> SaveSessionChunk {
+        SaveSessionChunk {
+            context: context("tenant"),
+            session_id: "session".to_string(),
+            data: data.to_vec(),
+            is_last,
+            total_size,
+            chunk_index: 0,
+        }
+    }
+
+    fn upload(
+        chunks: Vec<SaveSessionChunk>,
+    ) -> impl Stream<Item = Result<SaveSessionChunk, Status>> + Unpin {
+        tokio_stream::iter(chunks.into_iter().map(Ok))
+    }
+
+    /// Log output captured by a test subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_span_fields_reach_streaming_task() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        // RPC spans must be enabled at the default info level for their fields
+        // to reach the (debug) logs of the streaming task
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish()
+            .with(filter_fn(|meta| {
+                meta.is_event() || *meta.level() <= tracing::Level::INFO
+            }));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let (service, _temp) = setup(1024);
+        service
+            .storage
+            .save_session("tenant-a", "session-1", &[1u8; 4096])
+            .await
+            .unwrap();
+
+        let response = service
+            .load_session(Request::new(LoadSessionRequest {
+                context: context("tenant-a"),
+                session_id: "session-1".to_string(),
+            }))
+            .await
+            .unwrap();
+        let chunks: Vec<_> = response.into_inner().collect().await;
+        assert_eq!(chunks.len(), 4);
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let line = logs
+            .lines()
+            .find(|l| l.contains("Streamed 4096 bytes"))
+            .unwrap_or_else(|| panic!("streaming task did not log:\n{}", logs));
+        assert!(line.contains("tenant_id=\"tenant-a\""), "{}", line);
+        assert!(line.contains("session_id=\"session-1\""), "{}", line);
+    }
+
+    #[tokio::test]
+    async fn test_save_session_rejects_truncated_upload() {
+        let (service, _temp) = setup(64 * 1024);
+
+        // Fewer bytes than declared
+        let chunks = vec![upload_chunk(b"PK\x03\x04", 100, true)];
+        let status = service
+            .save_session_stream(upload(chunks))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+
+        // Stream ends without is_last
+        let chunks = vec![upload_chunk(b"PK\x03\x04", 0, false)];
+        let status = service
+            .save_session_stream(upload(chunks))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+
+        assert!(!service.storage.session_exists("tenant", "session").await.unwrap());
+
+        // Matching size is saved
+        let chunks = vec![
+            upload_chunk(b"PK\x03", 4, false),
+            upload_chunk(b"\x04", 0, true),
+        ];
+        service
+            .save_session_stream(upload(chunks))
+            .await
+            .unwrap();
+        let saved = service.storage.load_session("tenant", "session").await.unwrap();
+        assert_eq!(saved.as_deref(), Some(&b"PK\x03\x04"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_chunk_index() {
+        let (service, _temp) = setup(64 * 1024);
+        let numbered = |data: &[u8], chunk_index, is_last| SaveSessionChunk {
+            chunk_index,
+            ..upload_chunk(data, 0, is_last)
+        };
+
+        // A missing chunk, and numbering that starts midway
+        for indexes in [[1, 3], [0, 2]] {
+            let chunks = vec![
+                numbered(b"PK\x03", indexes[0], false),
+                numbered(b"\x04", indexes[1], true),
+            ];
+            let status = service
+                .save_session_stream(upload(chunks))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::DataLoss);
+        }
+        assert!(!service.storage.session_exists("tenant", "session").await.unwrap());
+
+        let chunks = vec![numbered(b"PK\x03", 1, false), numbered(b"\x04", 2, true)];
+        service
+            .save_session_stream(upload(chunks))
+            .await
+            .unwrap();
+        assert!(service.storage.session_exists("tenant", "session").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_save_checkpoint_rejects_truncated_upload() {
+        let (service, _temp) = setup(64 * 1024);
+        let chunk = |is_last| SaveCheckpointChunk {
+            context: context("tenant"),
+            session_id: "session".to_string(),
+            position: 3,
+            data: b"ckpt".to_vec(),
+            is_last,
+            chunk_index: 0,
+        };
+
+        let chunks = tokio_stream::iter(vec![Ok(chunk(false))]);
+        let status = service.save_checkpoint_stream(chunks).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+        let checkpoints = service.storage.list_checkpoints("tenant", "session").await.unwrap();
+        assert!(checkpoints.is_empty());
+
+        let chunks = tokio_stream::iter(vec![Ok(chunk(true))]);
+        service.save_checkpoint_stream(chunks).await.unwrap();
+        let checkpoints = service.storage.list_checkpoints("tenant", "session").await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_bundle_rejects_truncated_upload() {
+        let (service, _temp) = setup(64 * 1024);
+        let chunks = tokio_stream::iter(vec![Ok(ImportBundleChunk {
+            context: context("tenant"),
+            data: b"partial bundle".to_vec(),
+            is_last: false,
+            chunk_index: 0,
+        })]);
+
+        let status = service.import_bundle_stream(chunks).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DataLoss);
+    }
+
+    #[test]
+    fn test_validate_tenant_id() {
+        for valid in ["tenant-a", "Tenant_42", "0f8fad5b-d9cb-469f-a165-70867728950e"] {
+            assert_eq!(StorageServiceImpl::validate_tenant_id(valid).unwrap(), valid);
+        }
+        for invalid in ["", "..", "../other", "a/b", "a\\b", "tenant.a", "tenant a", "ténant"] {
+            let status = StorageServiceImpl::validate_tenant_id(invalid).unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_traversal_tenant() {
+        // A base dir inside the temp dir, so that escaping it stays observable
+        let temp = TempDir::new().unwrap();
+        let base = temp.path().join("base");
+        let service = StorageServiceImpl::new(
+            Arc::new(LocalStorage::new(&base)),
+            Arc::new(FileLock::new(&base)),
+            64 * 1024,
+        );
+
+        for tenant in ["../other", ".."] {
+            let status = service
+                .session_exists(Request::new(SessionExistsRequest {
+                    context: context(tenant),
+                    session_id: "session".to_string(),
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+            // Streaming uploads are validated before anything is written
+            let mut chunk = upload_chunk(b"PK", 0, true);
+            chunk.context = context(tenant);
+            let status = service
+                .save_session_stream(upload(vec![chunk]))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+
+        // Nothing was written next to the base dir, nor in it
+        let outside: Vec<_> = std::fs::read_dir(temp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .filter(|name| name != "base")
+            .collect();
+        assert!(outside.is_empty(), "{:?}", outside);
+        assert!(!base.exists() || std::fs::read_dir(&base).unwrap().next().is_none());
+    }
+
+    fn append_request(position: u64) -> Request<AppendWalRequest> {
+        Request::new(AppendWalRequest {
+            context: context("tenant"),
+            session_id: "session".to_string(),
+            entries: vec![WalEntry {
+                position,
+                operation: "add".to_string(),
+                path: "/body/paragraph[0]".to_string(),
+                patch_json: b"{}".to_vec(),
+                timestamp_unix: 0,
+            }],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_append_wal_recommends_checkpoint() {
+        let (service, _temp) = setup(64 * 1024);
+        let service = service.with_checkpoint_threshold(3);
+
+        let mut flags = Vec::new();
+        for position in 1..=3 {
+            let response = service.append_wal(append_request(position)).await.unwrap();
+            flags.push(response.into_inner().checkpoint_recommended);
+        }
+        assert_eq!(flags, vec![false, false, true]);
+
+        // A checkpoint at the latest position, as recorded in the index,
+        // resets the count
+        let now = chrono::Utc::now();
+        let mut index = SessionIndex::default();
+        index.sessions.insert(
+            "session".to_string(),
+            SessionIndexEntry {
+                source_path: None,
+                created_at: now,
+                modified_at: now,
+                wal_position: 3,
+                checkpoint_positions: vec![3],
+                forked_from: None,
+            },
+        );
+        service.storage.save_index("tenant", &index).await.unwrap();
+        let response = service.append_wal(append_request(4)).await.unwrap();
+        assert!(!response.into_inner().checkpoint_recommended);
+    }
+
+    #[tokio::test]
+    async fn test_gc_checkpoints_waits_for_index_lock() {
+        let (service, _temp) = setup(64 * 1024);
+        let service = Arc::new(service);
+        for position in [1, 2, 3] {
+            service
+                .storage
+                .save_checkpoint("tenant", "session", position, b"ckpt")
+                .await
+                .unwrap();
+        }
+
+        let ttl = Duration::from_secs(60);
+        let lock = service.lock_manager.clone();
+        lock.acquire("tenant", INDEX_LOCK_RESOURCE, "client", ttl, None)
+            .await
+            .unwrap();
+
+        let gc = tokio::spawn({
+            let service = service.clone();
+            async move {
+                service
+                    .gc_checkpoints(Request::new(GcCheckpointsRequest {
+                        context: context("tenant"),
+                        session_id: "session".to_string(),
+                        keep_latest: 1,
+                        keep_every: 0,
+                    }))
+                    .await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!gc.is_finished());
+        assert_eq!(service.storage.list_checkpoints("tenant", "session").await.unwrap().len(), 3);
+
+        lock.release("tenant", INDEX_LOCK_RESOURCE, "client").await.unwrap();
+        let response = gc.await.unwrap().unwrap().into_inner();
+        assert_eq!(response.removed_positions, vec![1, 2]);
+
+        // The service released the index lock behind it
+        assert!(lock.list_locks("tenant").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repair_wal_waits_for_session_lock() {
+        let (service, _temp) = setup(64 * 1024);
+        let service = Arc::new(service);
+
+        let ttl = Duration::from_secs(60);
+        let lock = service.lock_manager.clone();
+        lock.acquire("tenant", "session", "client", ttl, None)
+            .await
+            .unwrap();
+
+        let repair = tokio::spawn({
+            let service = service.clone();
+            async move {
+                service
+                    .repair_wal(Request::new(RepairWalRequest {
+                        context: context("tenant"),
+                        session_id: "session".to_string(),
+                    }))
+                    .await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!repair.is_finished());
+
+        lock.release("tenant", "session", "client").await.unwrap();
+        repair.await.unwrap().unwrap();
+
+        // Both the session and the index lock were released
+        assert!(lock.list_locks("tenant").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_bundle_size_limit() {
+        let (service, _temp) = setup(64 * 1024);
+        let service = service.with_max_bundle_size(10);
+
+        let chunk = |is_last| ImportBundleChunk {
+            context: context("tenant"),
+            data: vec![0u8; 8],
+            is_last,
+            chunk_index: 0,
+        };
+        let chunks = tokio_stream::iter(vec![Ok(chunk(false)), Ok(chunk(true))]);
+
+        let status = service.import_bundle_stream(chunks).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+
+    #[test]
+    fn test_checkpoint_recommended_policy() {
+        assert!(!checkpoint_recommended(49, None, 50));
+        assert!(checkpoint_recommended(50, None, 50));
+        assert!(!checkpoint_recommended(120, Some(100), 50));
+        assert!(checkpoint_recommended(150, Some(100), 50));
+        assert!(!checkpoint_recommended(1000, None, 0));
+    }
+}
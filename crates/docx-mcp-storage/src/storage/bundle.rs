@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::traits::{validate_id, SessionIndexEntry, WalEntry};
+use crate::error::StorageError;
+
+/// Identifies a session bundle archive.
+const BUNDLE_FORMAT: &str = "docx-mcp-session-bundle";
+
+/// Current bundle format version. Bump when the layout changes.
+pub const BUNDLE_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const SESSION_NAME: &str = "session.docx";
+const WAL_NAME: &str = "wal.jsonl";
+const CHECKPOINTS_DIR: &str = "checkpoints/";
+
+/// First entry of every bundle, describing its content.
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleManifest {
+    format: String,
+    version: u32,
+    session_id: String,
+    index_entry: Option<SessionIndexEntry>,
+    exported_at: chrono::DateTime<chrono::Utc>,
+    /// Hex SHA-256 of every other entry, by entry name. Required: every
+    /// version 1 bundle carries them.
+    #[serde(default)]
+    checksums: BTreeMap<String, String>,
+}
+
+/// A self-contained copy of one session, used to move it between tenants
+/// or deployments.
+///
+/// Serialized as a tar archive:
+/// ```text
+/// manifest.json              format, version, session id, index entry,
+///                            SHA-256 of the other entries
+/// session.docx
+/// wal.jsonl                  one WalEntry per line
+/// checkpoints/{position}.docx
+/// ```
+#[derive(Debug, Clone)]
+pub struct SessionBundle {
+    pub session_id: String,
+    pub index_entry: Option<SessionIndexEntry>,
+    pub session: Vec<u8>,
+    pub wal: Vec<WalEntry>,
+    pub checkpoints: Vec<(u64, Vec<u8>)>,
+}
+
+impl SessionBundle {
+    /// Serialize the bundle to a tar archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, StorageError> {
+        let mut wal = Vec::new();
+        for entry in &self.wal {
+            serde_json::to_writer(&mut wal, entry).map_err(|e| {
+                StorageError::Serialization(format!("Failed to serialize WAL entry: {}", e))
+            })?;
+            wal.push(b'\n');
+        }
+
+        let mut entries = vec![
+            (SESSION_NAME.to_string(), self.session.as_slice()),
+            (WAL_NAME.to_string(), wal.as_slice()),
+        ];
+        for (position, data) in &self.checkpoints {
+            entries.push((format!("{}{}.docx", CHECKPOINTS_DIR, position), data.as_slice()));
+        }
+
+        let manifest = BundleManifest {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            session_id: self.session_id.clone(),
+            index_entry: self.index_entry.clone(),
+            exported_at: chrono::Utc::now(),
+            checksums: entries
+                .iter()
+                .map(|(name, data)| (name.clone(), sha256(data)))
+                .collect(),
+        };
+        let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| {
+            StorageError::Serialization(format!("Failed to serialize bundle manifest: {}", e))
+        })?;
+
+        let mut builder = tar::Builder::new(Vec::new());
+        append(&mut builder, MANIFEST_NAME, &manifest)?;
+        for (name, data) in &entries {
+            append(&mut builder, name, data)?;
+        }
+
+        builder
+            .into_inner()
+            .map_err(|e| StorageError::io(e, "Failed to finish bundle"))
+    }
+
+    /// Parse a tar archive produced by [`SessionBundle::to_bytes`].
+    ///
+    /// A manifest without checksums, or entries whose content does not
+    /// match their checksum, fail with [`StorageError::ChecksumMismatch`].
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StorageError> {
+        let invalid =
+            |msg: String| StorageError::InvalidArgument(format!("Invalid bundle: {}", msg));
+
+        let mut archive = tar::Archive::new(data);
+        let mut manifest: Option<BundleManifest> = None;
+        let mut checksums = BTreeMap::new();
+        let mut session = None;
+        let mut wal = Vec::new();
+        let mut checkpoints = Vec::new();
+
+        let entries = archive.entries().map_err(|e| invalid(e.to_string()))?;
+        for entry in entries {
+            let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+            let name = entry
+                .path()
+                .map_err(|e| invalid(e.to_string()))?
+                .to_string_lossy()
+                .to_string();
+            let mut content = Vec::new();
+            entry
+                .read_to_end(&mut content)
+                .map_err(|e| invalid(format!("failed to read {}: {}", name, e)))?;
+
+            // The manifest comes first so the version is checked before
+            // anything else is interpreted.
+            if manifest.is_none() {
+                if name != MANIFEST_NAME {
+                    return Err(invalid(format!(
+                        "expected {} first, found {}",
+                        MANIFEST_NAME, name
+                    )));
+                }
+                let mut parsed: BundleManifest = serde_json::from_slice(&content)
+                    .map_err(|e| invalid(format!("bad manifest: {}", e)))?;
+                if parsed.format != BUNDLE_FORMAT {
+                    return Err(invalid(format!("unknown format {}", parsed.format)));
+                }
+                if parsed.version > BUNDLE_VERSION {
+                    return Err(invalid(format!(
+                        "version {} is newer than supported version {}",
+                        parsed.version, BUNDLE_VERSION
+                    )));
+                }
+                // The session id becomes a file name on import
+                if validate_id("session_id", &parsed.session_id).is_err() {
+                    return Err(invalid(format!("unsafe session id {:?}", parsed.session_id)));
+                }
+                // Never let a stripped manifest turn off the integrity check
+                if parsed.checksums.is_empty() {
+                    return Err(StorageError::ChecksumMismatch(
+                        "bundle manifest has no checksums".to_string(),
+                    ));
+                }
+                checksums = std::mem::take(&mut parsed.checksums);
+                manifest = Some(parsed);
+                continue;
+            }
+
+            let expected = checksums
+                .remove(&name)
+                .ok_or_else(|| invalid(format!("no checksum for {}", name)))?;
+            if sha256(&content) != expected {
+                return Err(StorageError::ChecksumMismatch(format!(
+                    "bundle entry {} is corrupted",
+                    name
+                )));
+            }
+
+            if name == SESSION_NAME {
+                session = Some(content);
+            } else if name == WAL_NAME {
+                for line in content.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+                    let entry: WalEntry = serde_json::from_slice(line)
+                        .map_err(|e| invalid(format!("bad WAL entry: {}", e)))?;
+                    wal.push(entry);
+                }
+            } else if let Some(position) = name
+                .strip_prefix(CHECKPOINTS_DIR)
+                .and_then(|n| n.strip_suffix(".docx"))
+                .and_then(|n| n.parse::<u64>().ok())
+            {
+                checkpoints.push((position, content));
+            } else {
+                return Err(invalid(format!("unexpected entry {}", name)));
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| invalid("empty archive".to_string()))?;
+        if let Some(name) = checksums.into_keys().next() {
+            return Err(invalid(format!("missing {}", name)));
+        }
+        let session = session.ok_or_else(|| invalid(format!("missing {}", SESSION_NAME)))?;
+        checkpoints.sort_by_key(|(position, _)| *position);
+
+        Ok(Self {
+            session_id: manifest.session_id,
+            index_entry: manifest.index_entry,
+            session,
+            wal,
+            checkpoints,
+        })
+    }
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn append(
+    builder: &mut tar::Builder<Vec<u8>>,
+    name: &str,
+    data: &[u8],
+) -> Result<(), StorageError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    builder
+        .append_data(&mut header, name, data)
+        .map_err(|e| StorageError::io(e, format!("Failed to add {} to bundle", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> SessionBundle {
+        SessionBundle {
+            session_id: "session".to_string(),
+            index_entry: None,
+            session: b"docx".to_vec(),
+            wal: vec![],
+            checkpoints: vec![(5, b"ckpt".to_vec())],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let decoded = SessionBundle::from_bytes(&bundle().to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.session_id, "session");
+        assert_eq!(decoded.session, b"docx");
+        assert_eq!(decoded.checkpoints, vec![(5, b"ckpt".to_vec())]);
+    }
+
+    #[test]
+    fn test_rejects_unsafe_session_id() {
+        for session_id in ["../../victim/sessions/x", "a/b", ".."] {
+            let data = SessionBundle {
+                session_id: session_id.to_string(),
+                ..bundle()
+            }
+            .to_bytes()
+            .unwrap();
+
+            let err = SessionBundle::from_bytes(&data).unwrap_err();
+            assert!(err.to_string().contains("unsafe session id"));
+        }
+    }
+
+    #[test]
+    fn test_rejects_corrupted_entry() {
+        let mut data = bundle().to_bytes().unwrap();
+        let at = data.windows(4).position(|w| w == b"ckpt").unwrap();
+        data[at] = b'C';
+
+        let err = SessionBundle::from_bytes(&data).unwrap_err();
+        assert!(matches!(err, StorageError::ChecksumMismatch(_)));
+    }
+
+    #[test]
+    fn test_rejects_missing_checksums() {
+        let manifest = serde_json::json!({
+            "format": BUNDLE_FORMAT,
+            "version": BUNDLE_VERSION,
+            "session_id": "session",
+            "index_entry": null,
+            "exported_at": chrono::Utc::now(),
+        });
+        let mut builder = tar::Builder::new(Vec::new());
+        append(&mut builder, MANIFEST_NAME, manifest.to_string().as_bytes()).unwrap();
+        append(&mut builder, SESSION_NAME, b"tampered").unwrap();
+        let data = builder.into_inner().unwrap();
+
+        let err = SessionBundle::from_bytes(&data).unwrap_err();
+        assert!(matches!(err, StorageError::ChecksumMismatch(_)));
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let manifest = serde_json::json!({
+            "format": BUNDLE_FORMAT,
+            "version": BUNDLE_VERSION + 1,
+            "session_id": "session",
+            "index_entry": null,
+            "exported_at": chrono::Utc::now(),
+        });
+        let mut builder = tar::Builder::new(Vec::new());
+        append(&mut builder, MANIFEST_NAME, manifest.to_string().as_bytes()).unwrap();
+        let data = builder.into_inner().unwrap();
+
+        let err = SessionBundle::from_bytes(&data).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+}
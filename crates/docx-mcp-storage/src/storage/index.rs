@@ -0,0 +1,155 @@
+use serde_json::{Map, Value};
+
+use super::traits::SessionIndex;
+use crate::error::StorageError;
+
+/// Current session index format version. Bump when the layout changes and
+/// add the matching upgrade to [`SessionIndex::from_json`].
+///
+/// Version 1 is the list layout of the .NET `SessionIndexFile`, so the map
+/// layout starts at 2.
+pub const SESSION_INDEX_VERSION: u32 = 2;
+
+impl SessionIndex {
+    /// Parse a stored index, upgrading older layouts to the current one.
+    ///
+    /// Returns the index and whether it had to be migrated. The upgrade only
+    /// happens in memory; the stored index keeps its layout until the next
+    /// save. Recognized layouts:
+    ///
+    /// ```text
+    /// {"version": 2, "sessions": {"<id>": {...}}}      current
+    /// {"sessions": {"<id>": {...}}}                    before versioning
+    /// {"version": 1, "sessions": [{"id": ..., ...}]}   .NET SessionIndexFile
+    /// ```
+    ///
+    /// Indexes written by a newer version are rejected rather than misread.
+    pub fn from_json(json: &[u8]) -> Result<(Self, bool), StorageError> {
+        let parse_error = |e: serde_json::Error| {
+            StorageError::Serialization(format!("Failed to parse index: {}", e))
+        };
+
+        let mut value: Value = serde_json::from_slice(json).map_err(parse_error)?;
+        let Some(object) = value.as_object_mut() else {
+            return Err(StorageError::Serialization(
+                "Failed to parse index: not a JSON object".to_string(),
+            ));
+        };
+
+        let version = object.get("version").and_then(Value::as_u64).unwrap_or(0);
+        if version > u64::from(SESSION_INDEX_VERSION) {
+            return Err(StorageError::Serialization(format!(
+                "Index version {} is newer than supported version {}",
+                version, SESSION_INDEX_VERSION
+            )));
+        }
+        let mut migrated = version < u64::from(SESSION_INDEX_VERSION);
+
+        if let Some(Value::Array(sessions)) = object.get("sessions") {
+            let sessions = from_dotnet_sessions(sessions)?;
+            object.insert("sessions".to_string(), Value::Object(sessions));
+            migrated = true;
+        }
+        object.insert("version".to_string(), SESSION_INDEX_VERSION.into());
+
+        let index = serde_json::from_value(value).map_err(parse_error)?;
+        Ok((index, migrated))
+    }
+}
+
+/// Convert the .NET session list into entries keyed by session id.
+///
+/// `last_modified_at` and `wal_count` map onto `modified_at` and
+/// `wal_position`; `docx_file` and `cursor_position` have no equivalent and
+/// are dropped.
+fn from_dotnet_sessions(sessions: &[Value]) -> Result<Map<String, Value>, StorageError> {
+    let mut entries = Map::new();
+    for session in sessions {
+        let id = session
+            .get("id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| {
+                StorageError::Serialization("Failed to parse index: session without id".to_string())
+            })?;
+        let field = |name: &str| session.get(name).cloned().unwrap_or(Value::Null);
+
+        let entry = serde_json::json!({
+            "source_path": field("source_path"),
+            "created_at": field("created_at"),
+            "modified_at": field("last_modified_at"),
+            "wal_position": session.get("wal_count").cloned().unwrap_or(0.into()),
+            "checkpoint_positions": session
+                .get("checkpoint_positions")
+                .cloned()
+                .unwrap_or_else(|| Value::Array(vec![])),
+        });
+        entries.insert(id.to_string(), entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An index as written by the .NET `SessionJsonContext`.
+    const DOTNET_INDEX: &str = r#"{
+  "version": 1,
+  "sessions": [
+    {
+      "id": "a1b2c3d4e5f6",
+      "source_path": "/home/user/report.docx",
+      "created_at": "2025-01-15T10:30:00.1234567Z",
+      "last_modified_at": "2025-01-15T11:00:00Z",
+      "docx_file": "a1b2c3d4e5f6.docx",
+      "wal_count": 12,
+      "cursor_position": 10,
+      "checkpoint_positions": [10]
+    }
+  ]
+}"#;
+
+    #[test]
+    fn test_migrates_dotnet_index() {
+        let (index, migrated) = SessionIndex::from_json(DOTNET_INDEX.as_bytes()).unwrap();
+        assert!(migrated);
+        assert_eq!(index.version, SESSION_INDEX_VERSION);
+
+        let entry = &index.sessions["a1b2c3d4e5f6"];
+        assert_eq!(entry.source_path.as_deref(), Some("/home/user/report.docx"));
+        assert_eq!(entry.wal_position, 12);
+        assert_eq!(entry.checkpoint_positions, vec![10]);
+        assert_eq!(entry.modified_at.to_rfc3339(), "2025-01-15T11:00:00+00:00");
+    }
+
+    #[test]
+    fn test_migrates_unversioned_index() {
+        let json = r#"{"sessions": {"s1": {
+            "source_path": null,
+            "created_at": "2025-01-15T10:30:00Z",
+            "modified_at": "2025-01-15T10:30:00Z",
+            "wal_position": 3,
+            "checkpoint_positions": []
+        }}}"#;
+        let (index, migrated) = SessionIndex::from_json(json.as_bytes()).unwrap();
+        assert!(migrated);
+        assert_eq!(index.version, SESSION_INDEX_VERSION);
+        assert_eq!(index.sessions["s1"].wal_position, 3);
+
+        // Saved back, it no longer needs migrating
+        let saved = serde_json::to_vec(&index).unwrap();
+        let (_, migrated) = SessionIndex::from_json(&saved).unwrap();
+        assert!(!migrated);
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let json = format!(
+            r#"{{"version": {}, "sessions": {{}}}}"#,
+            SESSION_INDEX_VERSION + 1
+        );
+        let err = SessionIndex::from_json(json.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+}
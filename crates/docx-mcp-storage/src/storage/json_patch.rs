@@ -0,0 +1,281 @@
+use serde_json::{json, Map, Value};
+
+use super::traits::WalEntry;
+use crate::error::StorageError;
+
+/// Operations that map one-to-one onto RFC 6902.
+const STANDARD_OPS: [&str; 5] = ["add", "replace", "remove", "move", "copy"];
+
+/// Path segments that select one element of a list. As in the .NET
+/// `PathParser`, a segment without a selector means the first one.
+const INDEXED_SEGMENTS: [&str; 11] = [
+    "paragraph", "table", "row", "cell", "run", "drawing", "hyperlink", "section", "bookmark",
+    "comment", "footnote",
+];
+
+/// Path segments whose positions do not index a list of their own:
+/// `children` counts every body element and `heading` a filtered subset of
+/// the paragraphs, so neither has a JSON Pointer form.
+const UNMAPPED_SEGMENTS: [&str; 2] = ["children", "heading"];
+
+impl WalEntry {
+    /// Convert the entry's patch into RFC 6902 JSON Patch operations.
+    ///
+    /// `patch_json` holds either one operation or an array of them, in the
+    /// format of the .NET `PatchOperation`. Document paths are rewritten as
+    /// JSON Pointers in which each list element kind (paragraphs, tables,
+    /// runs, ...) is an array indexed by its position selector:
+    ///
+    /// ```text
+    /// /body/paragraph[0]/run[1]/text  ->  /body/paragraph/0/run/1/text
+    /// /body/p[2]                      ->  /body/paragraph/2
+    /// /body/table/row[1]              ->  /body/table/0/row/1
+    /// ```
+    ///
+    /// Operations without an RFC 6902 equivalent (`replace_text`,
+    /// `remove_column`), paths using non-positional selectors such as
+    /// `[id='1A2B']` or `[text~='draft']`, and `children`/`heading` paths,
+    /// whose positions count across element kinds, are rejected.
+    pub fn as_json_patch(&self) -> Result<Vec<Value>, StorageError> {
+        let patch: Value = serde_json::from_slice(&self.patch_json).map_err(|e| {
+            StorageError::Serialization(format!(
+                "Failed to parse patch of WAL entry {}: {}",
+                self.position, e
+            ))
+        })?;
+
+        let operations = match patch {
+            Value::Array(operations) => operations,
+            operation => vec![operation],
+        };
+        operations.iter().map(to_json_patch_op).collect()
+    }
+}
+
+fn to_json_patch_op(operation: &Value) -> Result<Value, StorageError> {
+    let invalid = |msg: String| StorageError::InvalidArgument(msg);
+
+    let op = operation
+        .get("op")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("patch operation has no 'op'".to_string()))?;
+    if !STANDARD_OPS.contains(&op) {
+        return Err(invalid(format!(
+            "operation '{}' has no JSON Patch equivalent",
+            op
+        )));
+    }
+
+    let pointer = |field: &str| {
+        operation
+            .get(field)
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid(format!("'{}' operation has no '{}'", op, field)))
+            .and_then(to_json_pointer)
+    };
+
+    let mut result = Map::new();
+    result.insert("op".to_string(), json!(op));
+    result.insert("path".to_string(), json!(pointer("path")?));
+    match op {
+        "add" | "replace" => {
+            let value = operation
+                .get("value")
+                .ok_or_else(|| invalid(format!("'{}' operation has no 'value'", op)))?;
+            result.insert("value".to_string(), value.clone());
+        }
+        "move" | "copy" => {
+            result.insert("from".to_string(), json!(pointer("from")?));
+        }
+        _ => {}
+    }
+    Ok(Value::Object(result))
+}
+
+/// Convert a document path such as `/body/paragraph[0]/run[1]` into the
+/// JSON Pointer `/body/paragraph/0/run/1`. Segment names are matched
+/// case-insensitively, so `/body/Paragraph[0]` maps to the same pointer.
+fn to_json_pointer(path: &str) -> Result<String, StorageError> {
+    let mut pointer = String::new();
+    for segment in split_path(path) {
+        let (name, selector) = match segment.split_once('[') {
+            Some((name, rest)) => {
+                let selector = rest.strip_suffix(']').ok_or_else(|| {
+                    StorageError::InvalidArgument(format!("Malformed path segment '{}'", segment))
+                })?;
+                (name, Some(selector))
+            }
+            None => (segment, None),
+        };
+
+        let name = name.to_ascii_lowercase();
+        let name = if name == "p" { "paragraph" } else { &name };
+        if UNMAPPED_SEGMENTS.contains(&name) {
+            return Err(StorageError::InvalidArgument(format!(
+                "'{}' segment in '{}' has no JSON Pointer equivalent",
+                name, path
+            )));
+        }
+        pointer.push('/');
+        pointer.push_str(&name.replace('~', "~0").replace('/', "~1"));
+
+        let index: Option<u64> = match selector {
+            Some(selector) => Some(selector.trim().parse().map_err(|_| {
+                StorageError::InvalidArgument(format!(
+                    "Selector [{}] in '{}' is not a position",
+                    selector, path
+                ))
+            })?),
+            None if INDEXED_SEGMENTS.contains(&name) => Some(0),
+            None => None,
+        };
+        if let Some(index) = index {
+            pointer.push('/');
+            pointer.push_str(&index.to_string());
+        }
+    }
+
+    if pointer.is_empty() {
+        return Err(StorageError::InvalidArgument(format!(
+            "Path '{}' is empty",
+            path
+        )));
+    }
+    Ok(pointer)
+}
+
+/// Split a path on `/`, ignoring slashes inside `[...]` selectors.
+fn split_path(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in path.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            '/' if depth == 0 => {
+                if i > start {
+                    segments.push(&path[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < path.len() {
+        segments.push(&path[start..]);
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(patch: Value) -> WalEntry {
+        WalEntry {
+            position: 1,
+            operation: "patch".to_string(),
+            path: String::new(),
+            patch_json: patch.to_string().into_bytes(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_standard_operations() {
+        let value = json!({ "type": "paragraph", "text": "Hello" });
+        let patch = entry(json!([
+            { "op": "add", "path": "/body/paragraph[0]", "value": value },
+            { "op": "replace", "path": "/body/paragraph[1]/run[2]/text", "value": "Hi" },
+            { "op": "remove", "path": "/body/table[0]/row[3]" },
+            { "op": "move", "from": "/body/p[4]", "path": "/body/table[1]" },
+            { "op": "copy", "from": "/body/paragraph[5]", "path": "/body/paragraph[6]" },
+        ]))
+        .as_json_patch()
+        .unwrap();
+
+        assert_eq!(
+            patch,
+            vec![
+                json!({ "op": "add", "path": "/body/paragraph/0", "value": value }),
+                json!({ "op": "replace", "path": "/body/paragraph/1/run/2/text", "value": "Hi" }),
+                json!({ "op": "remove", "path": "/body/table/0/row/3" }),
+                json!({ "op": "move", "path": "/body/table/1", "from": "/body/paragraph/4" }),
+                json!({ "op": "copy", "path": "/body/paragraph/6", "from": "/body/paragraph/5" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_operation() {
+        let patch = entry(json!({ "op": "remove", "path": "/body/paragraph[0]" }))
+            .as_json_patch()
+            .unwrap();
+        assert_eq!(
+            patch,
+            vec![json!({ "op": "remove", "path": "/body/paragraph/0" })]
+        );
+    }
+
+    #[test]
+    fn test_missing_selector_is_first_element() {
+        let patch = entry(json!([
+            { "op": "remove", "path": "/body/paragraph" },
+            { "op": "replace", "path": "/body/table/row[2]/cell/text", "value": "x" },
+        ]))
+        .as_json_patch()
+        .unwrap();
+        assert_eq!(
+            patch,
+            vec![
+                json!({ "op": "remove", "path": "/body/paragraph/0" }),
+                json!({ "op": "replace", "path": "/body/table/0/row/2/cell/0/text", "value": "x" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segment_names_are_lowercased() {
+        let patch = entry(json!([
+            { "op": "remove", "path": "/Body/Paragraph[0]" },
+            { "op": "remove", "path": "/body/P[1]/RUN[2]" },
+        ]))
+        .as_json_patch()
+        .unwrap();
+        assert_eq!(
+            patch,
+            vec![
+                json!({ "op": "remove", "path": "/body/paragraph/0" }),
+                json!({ "op": "remove", "path": "/body/paragraph/1/run/2" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_unsupported() {
+        let replace_text = entry(json!([
+            { "op": "replace_text", "path": "/body/paragraph[0]", "find": "a", "replace": "b" }
+        ]));
+        assert!(matches!(
+            replace_text.as_json_patch(),
+            Err(StorageError::InvalidArgument(_))
+        ));
+
+        let by_id = entry(json!({ "op": "remove", "path": "/body/paragraph[id='1A2B']" }));
+        assert!(matches!(
+            by_id.as_json_patch(),
+            Err(StorageError::InvalidArgument(_))
+        ));
+
+        // Positions that do not index a single element kind
+        for path in ["/body/children/3", "/body/heading[1]", "/body/Heading"] {
+            let patch = entry(json!({ "op": "remove", "path": path }));
+            assert!(
+                matches!(patch.as_json_patch(), Err(StorageError::InvalidArgument(_))),
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
+use futures::{future, StreamExt, TryStreamExt};
 use tokio::fs;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tracing::{debug, instrument, warn};
+use tracing::{debug, info, instrument, warn};
 
 use super::traits::{
-    CheckpointInfo, SessionIndex, SessionInfo, StorageBackend, WalEntry,
+    checkpoints_to_gc, validate_id, CheckpointInfo, SessionIndex, SessionIndexEntry, SessionInfo,
+    StorageBackend, StorageUsage, WalEntry, WalEntryStream, WalRepairReport,
 };
 use crate::error::StorageError;
 
@@ -25,6 +27,7 @@ use crate::error::StorageError;
 #[derive(Debug, Clone)]
 pub struct LocalStorage {
     base_dir: PathBuf,
+    durable: bool,
 }
 
 impl LocalStorage {
@@ -32,9 +35,21 @@ impl LocalStorage {
     pub fn new(base_dir: impl AsRef<Path>) -> Self {
         Self {
             base_dir: base_dir.as_ref().to_path_buf(),
+            durable: false,
         }
     }
 
+    /// Fsync files before renaming them into place, and their directory after.
+    ///
+    /// Without this a power loss shortly after a write can lose it on some
+    /// filesystems, even though the rename itself is atomic. Each write then
+    /// waits for the disk (two fsyncs per file, one per WAL append), which
+    /// typically adds milliseconds, so it is off by default.
+    pub fn with_durable_writes(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
     /// Get the sessions directory for a tenant.
     fn sessions_dir(&self, tenant_id: &str) -> PathBuf {
         self.base_dir.join(tenant_id).join("sessions")
@@ -63,16 +78,150 @@ impl LocalStorage {
         self.sessions_dir(tenant_id).join("index.json")
     }
 
+    /// Copy a file if it exists. Returns whether it existed.
+    async fn copy_if_exists(from: &Path, to: &Path) -> Result<bool, StorageError> {
+        match fs::copy(from, to).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(StorageError::io(
+                e,
+                format!("Failed to copy {} to {}", from.display(), to.display()),
+            )),
+        }
+    }
+
+    /// Write `data` to `path` atomically via `temp_path`, honoring `durable`.
+    async fn write_atomic(
+        &self,
+        temp_path: &Path,
+        path: &Path,
+        data: &[u8],
+    ) -> Result<(), StorageError> {
+        let mut file = fs::File::create(temp_path).await.map_err(|e| {
+            StorageError::io(e, format!("Failed to create {}", temp_path.display()))
+        })?;
+        file.write_all(data).await.map_err(|e| {
+            StorageError::io(e, format!("Failed to write {}", temp_path.display()))
+        })?;
+        file.flush().await.map_err(|e| {
+            StorageError::io(e, format!("Failed to flush {}", temp_path.display()))
+        })?;
+        self.finish_atomic(file, temp_path, path).await
+    }
+
+    /// Replace a session's WAL with `entries`, atomically.
+    async fn rewrite_wal(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+        entries: &[WalEntry],
+    ) -> Result<(), StorageError> {
+        let mut data = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut data, entry).map_err(|e| {
+                StorageError::Serialization(format!("Failed to serialize WAL entry: {}", e))
+            })?;
+            data.push(b'\n');
+        }
+
+        let path = self.wal_path(tenant_id, session_id);
+        let temp_path = path.with_extension("wal.tmp");
+        self.write_atomic(&temp_path, &path, &data).await
+    }
+
+    /// Rename a fully written temp file into place, honoring `durable`.
+    async fn finish_atomic(
+        &self,
+        file: fs::File,
+        temp_path: &Path,
+        path: &Path,
+    ) -> Result<(), StorageError> {
+        if self.durable {
+            file.sync_all().await.map_err(|e| {
+                StorageError::io(e, format!("Failed to sync {}", temp_path.display()))
+            })?;
+        }
+        drop(file);
+
+        fs::rename(temp_path, path).await.map_err(|e| {
+            StorageError::io(e, format!("Failed to rename to {}", path.display()))
+        })?;
+
+        if self.durable {
+            if let Some(dir) = path.parent() {
+                Self::sync_dir(dir).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fsync a directory so that renames within it are persisted.
+    async fn sync_dir(dir: &Path) -> Result<(), StorageError> {
+        let dir_file = fs::File::open(dir).await.map_err(|e| {
+            StorageError::io(e, format!("Failed to open dir {}", dir.display()))
+        })?;
+        dir_file.sync_all().await.map_err(|e| {
+            StorageError::io(e, format!("Failed to sync dir {}", dir.display()))
+        })
+    }
+
     /// Ensure the sessions directory exists.
+    ///
+    /// In durable mode, a newly created directory is fsynced together with
+    /// its parents up to `base_dir` so the new entries survive a crash.
     async fn ensure_sessions_dir(&self, tenant_id: &str) -> Result<(), StorageError> {
         let dir = self.sessions_dir(tenant_id);
+        let created = self.durable && !fs::try_exists(&dir).await.unwrap_or(false);
         fs::create_dir_all(&dir).await.map_err(|e| {
-            StorageError::Io(format!("Failed to create sessions dir {}: {}", dir.display(), e))
+            StorageError::io(e, format!("Failed to create sessions dir {}", dir.display()))
         })?;
+
+        if created {
+            let mut current = dir.as_path();
+            Self::sync_dir(current).await?;
+            while current != self.base_dir {
+                match current.parent() {
+                    Some(parent) => current = parent,
+                    None => break,
+                }
+                Self::sync_dir(current).await?;
+            }
+        }
         Ok(())
     }
 }
 
+/// A file of a tenant's sessions directory, classified by name.
+#[derive(Debug, PartialEq, Eq)]
+enum SessionFile<'a> {
+    /// `{session_id}.docx`
+    Session(&'a str),
+    /// `{session_id}.wal`
+    Wal(&'a str),
+    /// `{session_id}.ckpt.{position}.docx`
+    Checkpoint(&'a str, u64),
+}
+
+impl<'a> SessionFile<'a> {
+    /// Classify a file name, or `None` for the index, temp files and
+    /// anything else not named after a session.
+    fn parse(file_name: &'a str) -> Option<Self> {
+        if let Some(session_id) = file_name.strip_suffix(".wal") {
+            return (!session_id.is_empty()).then_some(SessionFile::Wal(session_id));
+        }
+        let stem = file_name.strip_suffix(".docx")?;
+        let checkpoint = stem.rsplit_once(".ckpt.").and_then(|(session_id, position)| {
+            let numeric = !position.is_empty() && position.bytes().all(|b| b.is_ascii_digit());
+            let position = position.parse().ok().filter(|_| numeric)?;
+            (!session_id.is_empty()).then_some(SessionFile::Checkpoint(session_id, position))
+        });
+        match checkpoint {
+            Some(checkpoint) => Some(checkpoint),
+            None => (!stem.is_empty()).then_some(SessionFile::Session(stem)),
+        }
+    }
+}
+
 #[async_trait]
 impl StorageBackend for LocalStorage {
     fn backend_name(&self) -> &'static str {
@@ -96,11 +245,7 @@ impl StorageBackend for LocalStorage {
                 Ok(Some(data))
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(StorageError::Io(format!(
-                "Failed to read {}: {}",
-                path.display(),
-                e
-            ))),
+            Err(e) => Err(StorageError::io(e, format!("Failed to read {}", path.display()))),
         }
     }
 
@@ -116,12 +261,7 @@ impl StorageBackend for LocalStorage {
 
         // Write atomically via temp file
         let temp_path = path.with_extension("docx.tmp");
-        fs::write(&temp_path, data).await.map_err(|e| {
-            StorageError::Io(format!("Failed to write {}: {}", temp_path.display(), e))
-        })?;
-        fs::rename(&temp_path, &path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to rename to {}: {}", path.display(), e))
-        })?;
+        self.write_atomic(&temp_path, &path, data).await?;
 
         debug!("Saved session {} ({} bytes)", session_id, data.len());
         Ok(())
@@ -167,6 +307,22 @@ impl StorageBackend for LocalStorage {
         Ok(existed)
     }
 
+    #[instrument(skip(self), level = "debug")]
+    async fn delete_tenant(&self, tenant_id: &str) -> Result<bool, StorageError> {
+        // The tenant id becomes a path component: never let it escape base_dir
+        validate_id("tenant_id", tenant_id)?;
+
+        let dir = self.base_dir.join(tenant_id);
+        match fs::remove_dir_all(&dir).await {
+            Ok(()) => {
+                debug!("Deleted tenant {}", tenant_id);
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(StorageError::io(e, format!("Failed to delete {}", dir.display()))),
+        }
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn list_sessions(&self, tenant_id: &str) -> Result<Vec<SessionInfo>, StorageError> {
         let dir = self.sessions_dir(tenant_id);
@@ -174,27 +330,33 @@ impl StorageBackend for LocalStorage {
             return Ok(vec![]);
         }
 
+        // Source paths are only recorded in the index; a broken index must
+        // not hide the sessions themselves
+        let index = match self.load_index(tenant_id).await {
+            Ok(index) => index.unwrap_or_default(),
+            Err(e) => {
+                warn!(
+                    "Failed to load index for {}, listing without source paths: {}",
+                    tenant_id, e
+                );
+                SessionIndex::default()
+            }
+        };
+
         let mut sessions = Vec::new();
         let mut entries = fs::read_dir(&dir).await.map_err(|e| {
-            StorageError::Io(format!("Failed to read dir {}: {}", dir.display(), e))
+            StorageError::io(e, format!("Failed to read dir {}", dir.display()))
         })?;
 
         while let Some(entry) = entries.next_entry().await.map_err(|e| {
-            StorageError::Io(format!("Failed to read dir entry: {}", e))
+            StorageError::io(e, "Failed to read dir entry")
         })? {
-            let path = entry.path();
-            if path.extension().is_some_and(|ext| ext == "docx")
-                && !path
-                    .file_stem()
-                    .is_some_and(|s| s.to_string_lossy().contains(".ckpt."))
-            {
-                let session_id = path
-                    .file_stem()
-                    .map(|s| s.to_string_lossy().to_string())
-                    .unwrap_or_default();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(SessionFile::Session(session_id)) = SessionFile::parse(&file_name) {
+                let session_id = session_id.to_string();
 
                 let metadata = entry.metadata().await.map_err(|e| {
-                    StorageError::Io(format!("Failed to get metadata: {}", e))
+                    StorageError::io(e, "Failed to get metadata")
                 })?;
 
                 let created_at = metadata
@@ -206,9 +368,14 @@ impl StorageBackend for LocalStorage {
                     .map(chrono::DateTime::from)
                     .unwrap_or_else(|_| chrono::Utc::now());
 
+                let source_path = index
+                    .sessions
+                    .get(&session_id)
+                    .and_then(|entry| entry.source_path.clone());
+
                 sessions.push(SessionInfo {
                     session_id,
-                    source_path: None, // Would need to read from index
+                    source_path,
                     created_at,
                     modified_at,
                     size_bytes: metadata.len(),
@@ -230,6 +397,89 @@ impl StorageBackend for LocalStorage {
         Ok(path.exists())
     }
 
+    #[instrument(skip(self), level = "debug")]
+    async fn session_id_taken(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+    ) -> Result<bool, StorageError> {
+        Ok(self.session_exists(tenant_id, session_id).await?
+            || self.wal_path(tenant_id, session_id).exists()
+            || !self.list_checkpoints(tenant_id, session_id).await?.is_empty())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn fork_session(
+        &self,
+        tenant_id: &str,
+        src_session_id: &str,
+        new_session_id: &str,
+    ) -> Result<(), StorageError> {
+        // The new id becomes a file name: never let it escape the tenant
+        validate_id("session_id", new_session_id)?;
+        if !self.session_exists(tenant_id, src_session_id).await? {
+            return Err(StorageError::NotFound(format!(
+                "Session {} not found",
+                src_session_id
+            )));
+        }
+        if self.session_id_taken(tenant_id, new_session_id).await? {
+            return Err(StorageError::Conflict(format!(
+                "Session {} already exists",
+                new_session_id
+            )));
+        }
+
+        // Checkpoints and WAL first, the session file last: a fork only
+        // becomes visible once it is complete.
+        let checkpoints = self.list_checkpoints(tenant_id, src_session_id).await?;
+        for ckpt in &checkpoints {
+            Self::copy_if_exists(
+                &self.checkpoint_path(tenant_id, src_session_id, ckpt.position),
+                &self.checkpoint_path(tenant_id, new_session_id, ckpt.position),
+            )
+            .await?;
+        }
+        Self::copy_if_exists(
+            &self.wal_path(tenant_id, src_session_id),
+            &self.wal_path(tenant_id, new_session_id),
+        )
+        .await?;
+        Self::copy_if_exists(
+            &self.session_path(tenant_id, src_session_id),
+            &self.session_path(tenant_id, new_session_id),
+        )
+        .await?;
+
+        let mut index = self.load_index(tenant_id).await?.unwrap_or_default();
+        let now = chrono::Utc::now();
+        let entry = match index.sessions.get(src_session_id) {
+            Some(src) => SessionIndexEntry {
+                source_path: None,
+                created_at: now,
+                modified_at: now,
+                forked_from: Some(src_session_id.to_string()),
+                ..src.clone()
+            },
+            None => {
+                let (wal, _) = self.read_wal(tenant_id, new_session_id, 0, None).await?;
+                SessionIndexEntry {
+                    source_path: None,
+                    created_at: now,
+                    modified_at: now,
+                    wal_position: wal.last().map(|e| e.position).unwrap_or(0),
+                    checkpoint_positions: checkpoints.iter().map(|c| c.position).collect(),
+                    forked_from: Some(src_session_id.to_string()),
+                }
+            }
+        };
+        index.sessions.insert(new_session_id.to_string(), entry);
+        self.save_index(tenant_id, &index).await?;
+
+        debug!("Forked session {} into {}", src_session_id, new_session_id);
+        Ok(())
+    }
+
     // =========================================================================
     // Index Operations
     // =========================================================================
@@ -239,18 +489,20 @@ impl StorageBackend for LocalStorage {
         let path = self.index_path(tenant_id);
         match fs::read_to_string(&path).await {
             Ok(json) => {
-                let index: SessionIndex = serde_json::from_str(&json).map_err(|e| {
-                    StorageError::Serialization(format!("Failed to parse index: {}", e))
-                })?;
+                // Migrated in memory only: writing it back here would race
+                // with index updates, which hold the index lock
+                let (index, migrated) = SessionIndex::from_json(json.as_bytes())?;
+                if migrated {
+                    debug!(
+                        "Read index of tenant {} in an older layout, upgraded to version {}",
+                        tenant_id, index.version
+                    );
+                }
                 debug!("Loaded index with {} sessions", index.sessions.len());
                 Ok(Some(index))
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(StorageError::Io(format!(
-                "Failed to read index {}: {}",
-                path.display(),
-                e
-            ))),
+            Err(e) => Err(StorageError::io(e, format!("Failed to read index {}", path.display()))),
         }
     }
 
@@ -269,12 +521,7 @@ impl StorageBackend for LocalStorage {
 
         // Write atomically
         let temp_path = path.with_extension("json.tmp");
-        fs::write(&temp_path, &json).await.map_err(|e| {
-            StorageError::Io(format!("Failed to write index: {}", e))
-        })?;
-        fs::rename(&temp_path, &path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to rename index: {}", e))
-        })?;
+        self.write_atomic(&temp_path, &path, json.as_bytes()).await?;
 
         debug!("Saved index with {} sessions", index.sessions.len());
         Ok(())
@@ -297,13 +544,14 @@ impl StorageBackend for LocalStorage {
 
         self.ensure_sessions_dir(tenant_id).await?;
         let path = self.wal_path(tenant_id, session_id);
+        let created = self.durable && !fs::try_exists(&path).await.unwrap_or(false);
 
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(&path)
             .await
-            .map_err(|e| StorageError::Io(format!("Failed to open WAL {}: {}", path.display(), e)))?;
+            .map_err(|e| StorageError::io(e, format!("Failed to open WAL {}", path.display())))?;
 
         let mut last_position = 0u64;
         for entry in entries {
@@ -311,17 +559,28 @@ impl StorageBackend for LocalStorage {
                 StorageError::Serialization(format!("Failed to serialize WAL entry: {}", e))
             })?;
             file.write_all(line.as_bytes()).await.map_err(|e| {
-                StorageError::Io(format!("Failed to write WAL: {}", e))
+                StorageError::io(e, "Failed to write WAL")
             })?;
             file.write_all(b"\n").await.map_err(|e| {
-                StorageError::Io(format!("Failed to write WAL newline: {}", e))
+                StorageError::io(e, "Failed to write WAL newline")
             })?;
             last_position = entry.position;
         }
 
         file.flush().await.map_err(|e| {
-            StorageError::Io(format!("Failed to flush WAL: {}", e))
+            StorageError::io(e, "Failed to flush WAL")
         })?;
+        if self.durable {
+            file.sync_data().await.map_err(|e| {
+                StorageError::io(e, "Failed to sync WAL")
+            })?;
+            // A new WAL file is only durable once its directory entry is
+            if created {
+                if let Some(dir) = path.parent() {
+                    Self::sync_dir(dir).await?;
+                }
+            }
+        }
 
         debug!(
             "Appended {} WAL entries, last position: {}",
@@ -347,11 +606,7 @@ impl StorageBackend for LocalStorage {
                 return Ok((vec![], false));
             }
             Err(e) => {
-                return Err(StorageError::Io(format!(
-                    "Failed to open WAL {}: {}",
-                    path.display(),
-                    e
-                )));
+                return Err(StorageError::io(e, format!("Failed to open WAL {}", path.display())));
             }
         };
 
@@ -361,7 +616,7 @@ impl StorageBackend for LocalStorage {
         let limit = limit.unwrap_or(u64::MAX);
 
         while let Some(line) = lines.next_line().await.map_err(|e| {
-            StorageError::Io(format!("Failed to read WAL line: {}", e))
+            StorageError::io(e, "Failed to read WAL line")
         })? {
             if line.trim().is_empty() {
                 continue;
@@ -376,7 +631,7 @@ impl StorageBackend for LocalStorage {
                 if entries.len() as u64 >= limit {
                     // Check if there are more
                     let has_more = lines.next_line().await.map_err(|e| {
-                        StorageError::Io(format!("Failed to check for more WAL: {}", e))
+                        StorageError::io(e, "Failed to check for more WAL")
                     })?.is_some();
                     return Ok((entries, has_more));
                 }
@@ -391,6 +646,46 @@ impl StorageBackend for LocalStorage {
         Ok((entries, false))
     }
 
+    #[instrument(skip(self), level = "debug")]
+    async fn stream_wal(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+        from_position: u64,
+        limit: Option<u64>,
+    ) -> Result<WalEntryStream, StorageError> {
+        let path = self.wal_path(tenant_id, session_id);
+        let file = match fs::File::open(&path).await {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Box::pin(futures::stream::empty()));
+            }
+            Err(e) => {
+                return Err(StorageError::io(e, format!("Failed to open WAL {}", path.display())));
+            }
+        };
+
+        // Only the current line is held in memory
+        let lines = futures::stream::try_unfold(BufReader::new(file).lines(), |mut lines| async {
+            let line = lines.next_line().await?;
+            Ok(line.map(|line| (line, lines)))
+        });
+        let limit = limit.map_or(usize::MAX, |l| usize::try_from(l).unwrap_or(usize::MAX));
+
+        let entries = lines
+            .map_err(|e| StorageError::io(e, "Failed to read WAL line"))
+            .try_filter(|line| future::ready(!line.trim().is_empty()))
+            .and_then(|line| {
+                future::ready(serde_json::from_str::<WalEntry>(&line).map_err(|e| {
+                    StorageError::Serialization(format!("Failed to parse WAL entry: {}", e))
+                }))
+            })
+            .try_filter(move |entry| future::ready(entry.position >= from_position))
+            .take(limit);
+
+        Ok(Box::pin(entries))
+    }
+
     #[instrument(skip(self), level = "debug")]
     async fn truncate_wal(
         &self,
@@ -411,35 +706,88 @@ impl StorageBackend for LocalStorage {
         }
 
         // Rewrite WAL with only kept entries
-        let path = self.wal_path(tenant_id, session_id);
-        let temp_path = path.with_extension("wal.tmp");
+        self.rewrite_wal(tenant_id, session_id, &to_keep).await?;
 
-        let mut file = fs::File::create(&temp_path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to create temp WAL: {}", e))
-        })?;
+        debug!("Truncated WAL, removed {} entries", to_remove);
+        Ok(to_remove)
+    }
 
-        for entry in &to_keep {
-            let line = serde_json::to_string(entry).map_err(|e| {
-                StorageError::Serialization(format!("Failed to serialize WAL entry: {}", e))
-            })?;
-            file.write_all(line.as_bytes()).await.map_err(|e| {
-                StorageError::Io(format!("Failed to write WAL: {}", e))
-            })?;
-            file.write_all(b"\n").await.map_err(|e| {
-                StorageError::Io(format!("Failed to write WAL newline: {}", e))
-            })?;
-        }
+    #[instrument(skip(self), level = "debug")]
+    async fn repair_wal(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+    ) -> Result<WalRepairReport, StorageError> {
+        let path = self.wal_path(tenant_id, session_id);
+        let data = match fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(WalRepairReport::default());
+            }
+            Err(e) => {
+                return Err(StorageError::io(e, format!("Failed to read WAL {}", path.display())));
+            }
+        };
 
-        file.flush().await.map_err(|e| {
-            StorageError::Io(format!("Failed to flush temp WAL: {}", e))
-        })?;
+        let mut report = WalRepairReport::default();
+        let mut entries: Vec<WalEntry> = Vec::new();
+        let mut lines = data
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty());
+        for line in lines.by_ref() {
+            match serde_json::from_slice::<WalEntry>(line) {
+                Ok(entry) => {
+                    // Positions are referenced by checkpoints and the index,
+                    // so a gap ends the usable WAL instead of being renumbered
+                    let expected = entries.last().map(|previous| previous.position + 1);
+                    if expected.is_some_and(|expected| entry.position != expected) {
+                        warn!(
+                            "Dropping WAL of session {} from position {}: expected {}",
+                            session_id,
+                            entry.position,
+                            expected.unwrap_or_default()
+                        );
+                        report.dropped = 1;
+                        break;
+                    }
+                    entries.push(entry);
+                }
+                Err(e) => {
+                    warn!(
+                        "Dropping WAL of session {} from line {}: {}",
+                        session_id,
+                        entries.len() + 1,
+                        e
+                    );
+                    report.dropped = 1;
+                    break;
+                }
+            }
+        }
+        report.dropped += lines.count() as u64;
+        report.recovered = entries.len() as u64;
 
-        fs::rename(&temp_path, &path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to rename temp WAL: {}", e))
-        })?;
+        if report.dropped == 0 {
+            return Ok(report);
+        }
+        self.rewrite_wal(tenant_id, session_id, &entries).await?;
+
+        // Keep the index in sync with what is left on disk
+        let last_position = entries.last().map(|e| e.position).unwrap_or(0);
+        if let Some(mut index) = self.load_index(tenant_id).await? {
+            if let Some(entry) = index.sessions.get_mut(session_id) {
+                if entry.wal_position != last_position {
+                    entry.wal_position = last_position;
+                    self.save_index(tenant_id, &index).await?;
+                }
+            }
+        }
 
-        debug!("Truncated WAL, removed {} entries", to_remove);
-        Ok(to_remove)
+        info!(
+            "Repaired WAL of session {}: {} entries recovered, {} lines dropped",
+            session_id, report.recovered, report.dropped
+        );
+        Ok(report)
     }
 
     // =========================================================================
@@ -459,12 +807,7 @@ impl StorageBackend for LocalStorage {
 
         // Write atomically
         let temp_path = path.with_extension("docx.tmp");
-        fs::write(&temp_path, data).await.map_err(|e| {
-            StorageError::Io(format!("Failed to write checkpoint: {}", e))
-        })?;
-        fs::rename(&temp_path, &path).await.map_err(|e| {
-            StorageError::Io(format!("Failed to rename checkpoint: {}", e))
-        })?;
+        self.write_atomic(&temp_path, &path, data).await?;
 
         debug!(
             "Saved checkpoint at position {} ({} bytes)",
@@ -487,7 +830,7 @@ impl StorageBackend for LocalStorage {
             if let Some(latest) = checkpoints.last() {
                 let path = self.checkpoint_path(tenant_id, session_id, latest.position);
                 let data = fs::read(&path).await.map_err(|e| {
-                    StorageError::Io(format!("Failed to read checkpoint: {}", e))
+                    StorageError::io(e, "Failed to read checkpoint")
                 })?;
                 return Ok(Some((data, latest.position)));
             }
@@ -505,10 +848,7 @@ impl StorageBackend for LocalStorage {
                 Ok(Some((data, position)))
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(StorageError::Io(format!(
-                "Failed to read checkpoint: {}",
-                e
-            ))),
+            Err(e) => Err(StorageError::io(e, "Failed to read checkpoint")),
         }
     }
 
@@ -527,11 +867,11 @@ impl StorageBackend for LocalStorage {
         let mut checkpoints = Vec::new();
 
         let mut entries = fs::read_dir(&dir).await.map_err(|e| {
-            StorageError::Io(format!("Failed to read dir: {}", e))
+            StorageError::io(e, "Failed to read dir")
         })?;
 
         while let Some(entry) = entries.next_entry().await.map_err(|e| {
-            StorageError::Io(format!("Failed to read dir entry: {}", e))
+            StorageError::io(e, "Failed to read dir entry")
         })? {
             let path = entry.path();
             let file_name = path
@@ -548,7 +888,7 @@ impl StorageBackend for LocalStorage {
 
                 if let Ok(position) = position_str.parse::<u64>() {
                     let metadata = entry.metadata().await.map_err(|e| {
-                        StorageError::Io(format!("Failed to get metadata: {}", e))
+                        StorageError::io(e, "Failed to get metadata")
                     })?;
 
                     checkpoints.push(CheckpointInfo {
@@ -573,11 +913,104 @@ impl StorageBackend for LocalStorage {
         );
         Ok(checkpoints)
     }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn gc_checkpoints(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+        keep_latest: usize,
+        keep_every: Option<u64>,
+    ) -> Result<Vec<u64>, StorageError> {
+        let positions: Vec<u64> = self
+            .list_checkpoints(tenant_id, session_id)
+            .await?
+            .iter()
+            .map(|c| c.position)
+            .collect();
+
+        let to_delete = checkpoints_to_gc(&positions, keep_latest, keep_every);
+        if to_delete.is_empty() {
+            return Ok(vec![]);
+        }
+
+        for position in &to_delete {
+            let path = self.checkpoint_path(tenant_id, session_id, *position);
+            if let Err(e) = fs::remove_file(&path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(StorageError::io(
+                        e,
+                        format!("Failed to delete checkpoint {}", path.display()),
+                    ));
+                }
+            }
+        }
+
+        // Keep the index in sync with what is left on disk
+        if let Some(mut index) = self.load_index(tenant_id).await? {
+            if let Some(entry) = index.sessions.get_mut(session_id) {
+                entry
+                    .checkpoint_positions
+                    .retain(|position| !to_delete.contains(position));
+                self.save_index(tenant_id, &index).await?;
+            }
+        }
+
+        debug!(
+            "Garbage collected {} checkpoints for session {}",
+            to_delete.len(),
+            session_id
+        );
+        Ok(to_delete)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn storage_usage(&self, tenant_id: &str) -> Result<StorageUsage, StorageError> {
+        let dir = self.sessions_dir(tenant_id);
+        let mut usage = StorageUsage::default();
+        if !dir.exists() {
+            return Ok(usage);
+        }
+
+        let mut entries = fs::read_dir(&dir).await.map_err(|e| {
+            StorageError::io(e, format!("Failed to read dir {}", dir.display()))
+        })?;
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| StorageError::io(e, "Failed to read dir entry"))?
+        {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            // Skip index.json and temp files
+            let Some(file) = SessionFile::parse(&file_name) else {
+                continue;
+            };
+            let size = entry
+                .metadata()
+                .await
+                .map_err(|e| StorageError::io(e, "Failed to get metadata"))?
+                .len();
+
+            match file {
+                SessionFile::Session(_) => {
+                    usage.sessions_bytes += size;
+                    usage.count += 1;
+                }
+                SessionFile::Wal(_) => usage.wal_bytes += size,
+                SessionFile::Checkpoint(..) => usage.checkpoint_bytes += size,
+            }
+        }
+
+        Ok(usage)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::bundle::SessionBundle;
+    use crate::storage::SessionListQuery;
     use tempfile::TempDir;
 
     async fn setup() -> (LocalStorage, TempDir) {
@@ -663,6 +1096,178 @@ mod tests {
         assert_eq!(read_entries.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_stream_wal() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let session = "test-session";
+
+        let entries: Vec<_> = (1..=1000).map(wal_entry).collect();
+        storage.append_wal(tenant, session, &entries).await.unwrap();
+
+        let streamed: Vec<_> = storage
+            .stream_wal(tenant, session, 500, Some(10))
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let positions: Vec<_> = streamed.iter().map(|e| e.position).collect();
+        assert_eq!(positions, (500..510).collect::<Vec<_>>());
+
+        let tail: Vec<_> = storage
+            .stream_wal(tenant, session, 995, None)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(tail.len(), 6);
+
+        let missing = storage.stream_wal(tenant, "missing", 0, None).await.unwrap();
+        assert_eq!(missing.count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_durable_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp_dir.path()).with_durable_writes(true);
+        let tenant = "test-tenant";
+        let session = "test-session";
+
+        storage.save_session(tenant, session, b"PK\x03\x04").await.unwrap();
+        storage.save_checkpoint(tenant, session, 1, b"ckpt").await.unwrap();
+        storage.save_index(tenant, &SessionIndex::default()).await.unwrap();
+        storage
+            .append_wal(tenant, session, &[wal_entry(1), wal_entry(2)])
+            .await
+            .unwrap();
+        storage.truncate_wal(tenant, session, 2).await.unwrap();
+
+        assert_eq!(
+            storage.load_session(tenant, session).await.unwrap().as_deref(),
+            Some(&b"PK\x03\x04"[..])
+        );
+        assert!(storage.load_checkpoint(tenant, session, 1).await.unwrap().is_some());
+        let (entries, _) = storage.read_wal(tenant, session, 0, None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+
+        // No temp files are left behind
+        let leftovers = std::fs::read_dir(storage.sessions_dir(tenant))
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[tokio::test]
+    async fn test_wal_append_is_in_place() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let session = "test-session";
+        let path = storage.wal_path(tenant, session);
+
+        let entries: Vec<_> = (1..=1000).map(wal_entry).collect();
+        storage.append_wal(tenant, session, &entries).await.unwrap();
+        let before = std::fs::read(&path).unwrap();
+
+        // Appending only writes the new line: existing bytes are untouched,
+        // so the cost does not depend on how many entries are already there.
+        let entry = wal_entry(1001);
+        storage.append_wal(tenant, session, std::slice::from_ref(&entry)).await.unwrap();
+        let after = std::fs::read(&path).unwrap();
+
+        let line = serde_json::to_string(&entry).unwrap();
+        assert_eq!(after.len(), before.len() + line.len() + 1);
+        assert_eq!(&after[..before.len()], &before[..]);
+
+        let (read, _) = storage.read_wal(tenant, session, 1001, None).await.unwrap();
+        assert_eq!(read.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_repair_wal_drops_truncated_tail() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let session = "test-session";
+        let path = storage.wal_path(tenant, session);
+
+        storage
+            .append_wal(tenant, session, &[wal_entry(1), wal_entry(2), wal_entry(3)])
+            .await
+            .unwrap();
+        // A crash mid-append leaves half a line behind
+        let mut data = std::fs::read(&path).unwrap();
+        let line = serde_json::to_vec(&wal_entry(4)).unwrap();
+        data.extend_from_slice(&line[..line.len() / 2]);
+        std::fs::write(&path, &data).unwrap();
+        assert!(storage.read_wal(tenant, session, 0, None).await.is_err());
+
+        let report = storage.repair_wal(tenant, session).await.unwrap();
+        assert_eq!(
+            report,
+            WalRepairReport {
+                recovered: 3,
+                dropped: 1,
+            }
+        );
+
+        let (entries, _) = storage.read_wal(tenant, session, 0, None).await.unwrap();
+        let positions: Vec<_> = entries.iter().map(|e| e.position).collect();
+        assert_eq!(positions, vec![1, 2, 3]);
+
+        // Repairing a healthy WAL changes nothing
+        let report = storage.repair_wal(tenant, session).await.unwrap();
+        assert_eq!(report.recovered, 3);
+        assert_eq!(report.dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_repair_wal_drops_entries_after_gap() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let session = "test-session";
+
+        let now = chrono::Utc::now();
+        let mut index = SessionIndex::default();
+        index.sessions.insert(
+            session.to_string(),
+            SessionIndexEntry {
+                source_path: None,
+                created_at: now,
+                modified_at: now,
+                wal_position: 6,
+                checkpoint_positions: vec![],
+                forked_from: None,
+            },
+        );
+        storage.save_index(tenant, &index).await.unwrap();
+        storage
+            .append_wal(
+                tenant,
+                session,
+                &[wal_entry(3), wal_entry(4), wal_entry(6), wal_entry(7)],
+            )
+            .await
+            .unwrap();
+
+        let report = storage.repair_wal(tenant, session).await.unwrap();
+        assert_eq!(
+            report,
+            WalRepairReport {
+                recovered: 2,
+                dropped: 2,
+            }
+        );
+
+        // Kept positions are untouched and the index follows the WAL
+        let (entries, _) = storage.read_wal(tenant, session, 0, None).await.unwrap();
+        let positions: Vec<_> = entries.iter().map(|e| e.position).collect();
+        assert_eq!(positions, vec![3, 4]);
+        let index = storage.load_index(tenant).await.unwrap().unwrap();
+        assert_eq!(index.sessions[session].wal_position, 4);
+    }
+
     #[tokio::test]
     async fn test_checkpoint_operations() {
         let (storage, _temp) = setup().await;
@@ -690,6 +1295,475 @@ mod tests {
         assert_eq!(pos, 20);
     }
 
+    fn wal_entry(position: u64) -> WalEntry {
+        WalEntry {
+            position,
+            operation: "add".to_string(),
+            path: format!("/body/paragraph[{}]", position),
+            patch_json: b"{}".to_vec(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fork_session() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+
+        storage.save_session(tenant, "original", b"original docx").await.unwrap();
+        storage
+            .append_wal(tenant, "original", &[wal_entry(1), wal_entry(2)])
+            .await
+            .unwrap();
+        storage.save_checkpoint(tenant, "original", 2, b"ckpt").await.unwrap();
+
+        storage.fork_session(tenant, "original", "fork").await.unwrap();
+
+        // Both evolve separately
+        storage.append_wal(tenant, "fork", &[wal_entry(3)]).await.unwrap();
+        storage.save_session(tenant, "fork", b"forked docx").await.unwrap();
+
+        let (original_wal, _) = storage.read_wal(tenant, "original", 0, None).await.unwrap();
+        let (fork_wal, _) = storage.read_wal(tenant, "fork", 0, None).await.unwrap();
+        assert_eq!(original_wal.len(), 2);
+        assert_eq!(fork_wal.len(), 3);
+
+        let original = storage.load_session(tenant, "original").await.unwrap().unwrap();
+        assert_eq!(original, b"original docx");
+        assert!(storage.load_checkpoint(tenant, "fork", 2).await.unwrap().is_some());
+
+        let index = storage.load_index(tenant).await.unwrap().unwrap();
+        let entry = &index.sessions["fork"];
+        assert_eq!(entry.forked_from.as_deref(), Some("original"));
+        assert!(entry.source_path.is_none());
+        assert_eq!(entry.wal_position, 2);
+        assert_eq!(entry.checkpoint_positions, vec![2]);
+
+        // Forking onto an existing session is refused
+        assert!(storage.fork_session(tenant, "original", "fork").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fork_session_refuses_leftovers() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        storage.save_session(tenant, "original", b"original docx").await.unwrap();
+
+        // A WAL or a checkpoint without a session file, e.g. from an
+        // interrupted delete, is not overwritten or merged into the fork
+        storage.append_wal(tenant, "stale-wal", &[wal_entry(1)]).await.unwrap();
+        storage.save_checkpoint(tenant, "stale-ckpt", 5, b"ckpt").await.unwrap();
+
+        for target in ["stale-wal", "stale-ckpt"] {
+            assert!(matches!(
+                storage.fork_session(tenant, "original", target).await,
+                Err(StorageError::Conflict(_))
+            ));
+            assert!(!storage.session_exists(tenant, target).await.unwrap());
+        }
+        let (wal, _) = storage.read_wal(tenant, "stale-wal", 0, None).await.unwrap();
+        assert_eq!(wal.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fork_session_rejects_traversal() {
+        let (storage, temp) = setup().await;
+        storage.save_session("tenant-a", "original", b"docx").await.unwrap();
+        storage.append_wal("tenant-a", "original", &[wal_entry(1)]).await.unwrap();
+        storage.save_checkpoint("tenant-a", "original", 1, b"ckpt").await.unwrap();
+
+        for target in ["../../victim/sessions/x", "..", "x/y", ""] {
+            assert!(
+                matches!(
+                    storage.fork_session("tenant-a", "original", target).await,
+                    Err(StorageError::InvalidArgument(_))
+                ),
+                "{}",
+                target
+            );
+        }
+        assert!(!temp.path().join("victim").exists());
+        assert_eq!(storage.list_sessions("tenant-a").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_round_trip() {
+        let (storage, _temp) = setup().await;
+        let session = "bundled";
+
+        storage.save_session("tenant-a", session, b"docx bytes").await.unwrap();
+        storage
+            .append_wal("tenant-a", session, &[wal_entry(1), wal_entry(2), wal_entry(3)])
+            .await
+            .unwrap();
+        storage.save_checkpoint("tenant-a", session, 1, b"ckpt 1").await.unwrap();
+        storage.save_checkpoint("tenant-a", session, 3, b"ckpt 3").await.unwrap();
+
+        let mut index = SessionIndex::default();
+        index.sessions.insert(
+            session.to_string(),
+            SessionIndexEntry {
+                source_path: Some("/home/user/report.docx".to_string()),
+                created_at: chrono::Utc::now(),
+                modified_at: chrono::Utc::now(),
+                wal_position: 3,
+                checkpoint_positions: vec![1, 3],
+                forked_from: None,
+            },
+        );
+        storage.save_index("tenant-a", &index).await.unwrap();
+
+        let bundle = storage.export_bundle("tenant-a", session).await.unwrap();
+        let imported = storage.import_bundle("tenant-b", &bundle).await.unwrap();
+        assert_eq!(imported, session);
+
+        let data = storage.load_session("tenant-b", session).await.unwrap().unwrap();
+        assert_eq!(data, b"docx bytes");
+
+        let (wal_a, _) = storage.read_wal("tenant-a", session, 0, None).await.unwrap();
+        let (wal_b, _) = storage.read_wal("tenant-b", session, 0, None).await.unwrap();
+        let positions = |wal: &[WalEntry]| wal.iter().map(|e| e.position).collect::<Vec<_>>();
+        assert_eq!(positions(&wal_a), positions(&wal_b));
+        assert_eq!(wal_b[1].path, wal_a[1].path);
+
+        let checkpoints: Vec<u64> = storage
+            .list_checkpoints("tenant-b", session)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.position)
+            .collect();
+        assert_eq!(checkpoints, vec![1, 3]);
+        let (ckpt, _) = storage.load_checkpoint("tenant-b", session, 3).await.unwrap().unwrap();
+        assert_eq!(ckpt, b"ckpt 3");
+
+        let index_b = storage.load_index("tenant-b").await.unwrap().unwrap();
+        assert_eq!(index_b.sessions[session].checkpoint_positions, vec![1, 3]);
+        assert!(index_b.sessions[session].source_path.is_none());
+
+        // Importing twice is refused
+        assert!(storage.import_bundle("tenant-b", &bundle).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_bundle_without_index_entry() {
+        let (storage, _temp) = setup().await;
+        let bundle = SessionBundle {
+            session_id: "imported".to_string(),
+            index_entry: None,
+            session: b"docx".to_vec(),
+            wal: vec![wal_entry(1), wal_entry(2)],
+            checkpoints: vec![(2, b"ckpt".to_vec())],
+        };
+        storage.import_bundle("tenant", &bundle.to_bytes().unwrap()).await.unwrap();
+
+        let index = storage.load_index("tenant").await.unwrap().unwrap();
+        let entry = &index.sessions["imported"];
+        assert_eq!(entry.wal_position, 2);
+        assert_eq!(entry.checkpoint_positions, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_import_bundle_refuses_leftovers() {
+        let (storage, _temp) = setup().await;
+        let bundle = |session_id: &str| {
+            SessionBundle {
+                session_id: session_id.to_string(),
+                index_entry: None,
+                session: b"docx".to_vec(),
+                wal: vec![wal_entry(1), wal_entry(2)],
+                checkpoints: vec![(2, b"ckpt".to_vec())],
+            }
+            .to_bytes()
+            .unwrap()
+        };
+
+        // Leftovers of an interrupted delete are not merged into the import
+        storage.append_wal("tenant", "stale-wal", &[wal_entry(7)]).await.unwrap();
+        storage.save_checkpoint("tenant", "stale-ckpt", 5, b"old").await.unwrap();
+
+        for session_id in ["stale-wal", "stale-ckpt"] {
+            assert!(matches!(
+                storage.import_bundle("tenant", &bundle(session_id)).await,
+                Err(StorageError::Conflict(_))
+            ));
+            assert!(!storage.session_exists("tenant", session_id).await.unwrap());
+        }
+        let (wal, _) = storage.read_wal("tenant", "stale-wal", 0, None).await.unwrap();
+        assert_eq!(wal.len(), 1);
+        assert!(storage.list_checkpoints("tenant", "stale-wal").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_bundle_rejects_traversal() {
+        let (storage, temp) = setup().await;
+        let bundle = SessionBundle {
+            session_id: "../../victim/sessions/x".to_string(),
+            index_entry: None,
+            session: b"docx".to_vec(),
+            wal: vec![wal_entry(1)],
+            checkpoints: vec![(1, b"ckpt".to_vec())],
+        };
+
+        assert!(matches!(
+            storage.import_bundle("tenant", &bundle.to_bytes().unwrap()).await,
+            Err(StorageError::InvalidArgument(_))
+        ));
+        assert!(!temp.path().join("victim").exists());
+        assert!(!temp.path().join("tenant").exists());
+    }
+
+    #[tokio::test]
+    async fn test_gc_checkpoints() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        let session = "test-session";
+        let positions = [10, 20, 30, 40, 50, 60];
+
+        for position in positions {
+            storage.save_checkpoint(tenant, session, position, b"ckpt").await.unwrap();
+        }
+
+        let mut index = SessionIndex::default();
+        index.sessions.insert(
+            session.to_string(),
+            SessionIndexEntry {
+                source_path: None,
+                created_at: chrono::Utc::now(),
+                modified_at: chrono::Utc::now(),
+                wal_position: 60,
+                checkpoint_positions: positions.to_vec(),
+                forked_from: None,
+            },
+        );
+        storage.save_index(tenant, &index).await.unwrap();
+
+        let removed = storage.gc_checkpoints(tenant, session, 3, None).await.unwrap();
+        assert_eq!(removed, vec![10, 20, 30]);
+
+        let remaining: Vec<u64> = storage
+            .list_checkpoints(tenant, session)
+            .await
+            .unwrap()
+            .iter()
+            .map(|c| c.position)
+            .collect();
+        assert_eq!(remaining, vec![40, 50, 60]);
+
+        let index = storage.load_index(tenant).await.unwrap().unwrap();
+        assert_eq!(index.sessions[session].checkpoint_positions, vec![40, 50, 60]);
+    }
+
+    #[test]
+    fn test_checkpoints_to_gc_policy() {
+        let positions = [10, 20, 30, 40, 50, 60];
+
+        // The latest checkpoint always survives
+        assert_eq!(checkpoints_to_gc(&positions, 0, None), vec![10, 20, 30, 40, 50]);
+
+        // Every 20th position is kept as history
+        assert_eq!(checkpoints_to_gc(&positions, 2, Some(20)), vec![10, 30]);
+
+        // Nothing to delete
+        assert!(checkpoints_to_gc(&positions, 10, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_storage_usage() {
+        let (storage, _temp) = setup().await;
+
+        storage.save_session("tenant-a", "s1", &[0; 100]).await.unwrap();
+        storage.save_session("tenant-a", "s2", &[0; 50]).await.unwrap();
+        storage.save_checkpoint("tenant-a", "s1", 1, &[0; 30]).await.unwrap();
+        storage.append_wal("tenant-a", "s1", &[wal_entry(1)]).await.unwrap();
+        storage.save_session("tenant-b", "s1", &[0; 10]).await.unwrap();
+
+        // Files of a session whose id looks like a checkpoint name
+        storage.save_session("tenant-a", "s3.ckpt.x", &[0; 7]).await.unwrap();
+        storage.append_wal("tenant-a", "s3.ckpt.x", &[wal_entry(1)]).await.unwrap();
+
+        let usage = storage.storage_usage("tenant-a").await.unwrap();
+        assert_eq!(usage.sessions_bytes, 157);
+        assert_eq!(usage.checkpoint_bytes, 30);
+        assert!(usage.wal_bytes > 0);
+        assert_eq!(usage.count, 3);
+
+        let usage = storage.storage_usage("tenant-b").await.unwrap();
+        assert_eq!(
+            usage,
+            StorageUsage {
+                sessions_bytes: 10,
+                wal_bytes: 0,
+                checkpoint_bytes: 0,
+                count: 1,
+            }
+        );
+        assert_eq!(
+            storage.storage_usage("tenant-c").await.unwrap(),
+            StorageUsage::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_session_file() {
+        assert_eq!(SessionFile::parse("s1.docx"), Some(SessionFile::Session("s1")));
+        assert_eq!(SessionFile::parse("s1.wal"), Some(SessionFile::Wal("s1")));
+        assert_eq!(
+            SessionFile::parse("s1.ckpt.20.docx"),
+            Some(SessionFile::Checkpoint("s1", 20))
+        );
+        assert_eq!(
+            SessionFile::parse("s1.ckpt.draft.docx"),
+            Some(SessionFile::Session("s1.ckpt.draft"))
+        );
+        assert_eq!(SessionFile::parse("s1.ckpt.+1.docx"), Some(SessionFile::Session("s1.ckpt.+1")));
+        for ignored in ["index.json", "index.json.tmp", "s1.docx.tmp", ".docx", ".wal"] {
+            assert_eq!(SessionFile::parse(ignored), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_tenant() {
+        let (storage, _temp) = setup().await;
+
+        storage.save_session("tenant-a", "s1", b"docx").await.unwrap();
+        storage.save_checkpoint("tenant-a", "s1", 1, b"ckpt").await.unwrap();
+        storage.append_wal("tenant-a", "s1", &[wal_entry(1)]).await.unwrap();
+        storage.save_index("tenant-a", &SessionIndex::default()).await.unwrap();
+        storage.save_session("tenant-b", "s1", b"docx").await.unwrap();
+
+        assert!(storage.delete_tenant("tenant-a").await.unwrap());
+        assert!(storage.list_sessions("tenant-a").await.unwrap().is_empty());
+        assert!(storage.load_index("tenant-a").await.unwrap().is_none());
+        assert!(!storage.delete_tenant("tenant-a").await.unwrap());
+
+        // Other tenants are untouched
+        assert!(storage.session_exists("tenant-b", "s1").await.unwrap());
+
+        assert!(matches!(
+            storage.delete_tenant("..").await,
+            Err(StorageError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            storage.delete_tenant("tenant-b/sessions").await,
+            Err(StorageError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            storage.delete_tenant("tenant.b").await,
+            Err(StorageError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_page() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        for i in (1..=5).rev() {
+            storage
+                .save_session(tenant, &format!("session-{}", i), b"docx")
+                .await
+                .unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut query = SessionListQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut page_sizes = Vec::new();
+        loop {
+            let page = storage.list_sessions_page(tenant, &query).await.unwrap();
+            assert_eq!(page.total, 5);
+            if page.sessions.is_empty() {
+                break;
+            }
+            page_sizes.push(page.sessions.len());
+            seen.extend(page.sessions.into_iter().map(|s| s.session_id));
+            query.offset += 2;
+        }
+
+        assert_eq!(page_sizes, vec![2, 2, 1]);
+        assert_eq!(
+            seen,
+            (1..=5).map(|i| format!("session-{}", i)).collect::<Vec<_>>()
+        );
+
+        let query = SessionListQuery {
+            modified_after: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        let page = storage.list_sessions_page(tenant, &query).await.unwrap();
+        assert_eq!(page.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_load_index_migrates_in_memory() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        storage.ensure_sessions_dir(tenant).await.unwrap();
+        let path = storage.index_path(tenant);
+        std::fs::write(&path, r#"{"sessions": {}}"#).unwrap();
+
+        let index = storage.load_index(tenant).await.unwrap().unwrap();
+        assert_eq!(index.version, crate::storage::SESSION_INDEX_VERSION);
+
+        // The stored index is left alone until the next save
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"sessions": {}}"#);
+        storage.save_index(tenant, &index).await.unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], crate::storage::SESSION_INDEX_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions_source_path() {
+        let (storage, _temp) = setup().await;
+        let tenant = "test-tenant";
+        storage.save_session(tenant, "synced", b"docx").await.unwrap();
+        storage.save_session(tenant, "scratch", b"docx").await.unwrap();
+
+        let now = chrono::Utc::now();
+        let mut index = SessionIndex::default();
+        index.sessions.insert(
+            "synced".to_string(),
+            SessionIndexEntry {
+                source_path: Some("/docs/report.docx".to_string()),
+                created_at: now,
+                modified_at: now,
+                wal_position: 0,
+                checkpoint_positions: vec![],
+                forked_from: None,
+            },
+        );
+        storage.save_index(tenant, &index).await.unwrap();
+
+        let sessions = storage.list_sessions(tenant).await.unwrap();
+        let source_path = |id: &str| {
+            sessions
+                .iter()
+                .find(|s| s.session_id == id)
+                .unwrap()
+                .source_path
+                .clone()
+        };
+        assert_eq!(source_path("synced").as_deref(), Some("/docs/report.docx"));
+        assert_eq!(source_path("scratch"), None);
+
+        let query = SessionListQuery {
+            source_path_prefix: Some("/docs/".to_string()),
+            ..Default::default()
+        };
+        let page = storage.list_sessions_page(tenant, &query).await.unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.sessions[0].session_id, "synced");
+
+        // A corrupt index still lists the sessions, without source paths
+        std::fs::write(storage.index_path(tenant), b"{not json").unwrap();
+        let sessions = storage.list_sessions(tenant).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().all(|s| s.source_path.is_none()));
+    }
+
     #[tokio::test]
     async fn test_tenant_isolation() {
         let (storage, _temp) = setup().await;
@@ -1,7 +1,11 @@
 mod traits;
+mod bundle;
+mod index;
+mod json_patch;
 mod local;
 
 pub use traits::*;
+pub use index::SESSION_INDEX_VERSION;
 pub use local::LocalStorage;
 
 #[cfg(feature = "cloud")]
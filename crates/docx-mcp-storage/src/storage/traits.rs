@@ -1,6 +1,8 @@
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 
+use super::bundle::SessionBundle;
 use crate::error::StorageError;
 
 /// Information about a session stored in the backend.
@@ -13,6 +15,27 @@ pub struct SessionInfo {
     pub size_bytes: u64,
 }
 
+/// Paging and filtering for [`StorageBackend::list_sessions_page`].
+#[derive(Debug, Clone, Default)]
+pub struct SessionListQuery {
+    /// Number of matching sessions to skip.
+    pub offset: usize,
+    /// Maximum number of sessions to return; `None` returns all the rest.
+    pub limit: Option<usize>,
+    /// Only sessions whose source path starts with this prefix.
+    pub source_path_prefix: Option<String>,
+    /// Only sessions modified strictly after this instant.
+    pub modified_after: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// One page of sessions.
+#[derive(Debug, Clone)]
+pub struct SessionPage {
+    pub sessions: Vec<SessionInfo>,
+    /// Number of sessions matching the filters, across all pages.
+    pub total: usize,
+}
+
 /// A single WAL entry representing an edit operation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalEntry {
@@ -31,12 +54,109 @@ pub struct CheckpointInfo {
     pub size_bytes: u64,
 }
 
+/// Bytes used by one tenant, broken down by artifact type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub sessions_bytes: u64,
+    pub wal_bytes: u64,
+    pub checkpoint_bytes: u64,
+    /// Number of sessions.
+    pub count: u64,
+}
+
+/// Select the checkpoint positions a retention policy would delete.
+///
+/// The `keep_latest` highest positions are kept, and the very latest one is
+/// always kept even when `keep_latest` is 0. With `keep_every = Some(n)`,
+/// positions that are a multiple of `n` are kept as well, to preserve a
+/// sparse history. The result is sorted ascending.
+pub fn checkpoints_to_gc(
+    positions: &[u64],
+    keep_latest: usize,
+    keep_every: Option<u64>,
+) -> Vec<u64> {
+    let mut sorted = positions.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let keep_latest = keep_latest.max(1);
+    let cutoff = sorted.len().saturating_sub(keep_latest);
+
+    sorted[..cutoff]
+        .iter()
+        .copied()
+        .filter(|position| match keep_every {
+            Some(n) if n > 0 => position % n != 0,
+            _ => true,
+        })
+        .collect()
+}
+
+/// Whether the WAL has grown enough since the last checkpoint that the
+/// caller should create a new one. Mirrors the .NET compaction rule: the
+/// count of entries since the checkpoint reaching `threshold`. A `threshold`
+/// of 0 disables the recommendation.
+pub fn checkpoint_recommended(
+    wal_position: u64,
+    last_checkpoint: Option<u64>,
+    threshold: u64,
+) -> bool {
+    threshold > 0 && wal_position.saturating_sub(last_checkpoint.unwrap_or(0)) >= threshold
+}
+
+/// Check that a tenant or session id is a plain identifier.
+///
+/// Backends use these ids as directory and file names or key prefixes, so
+/// only `[A-Za-z0-9_-]` is accepted: no separators, dots or `..` traversal.
+/// `kind` names the id in the error message, e.g. `"tenant_id"`.
+pub fn validate_id(kind: &str, id: &str) -> Result<(), StorageError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidArgument(format!(
+            "invalid {} {:?}: only letters, digits, '_' and '-' are allowed",
+            kind, id
+        )))
+    }
+}
+
+/// WAL entries produced one at a time by [`StorageBackend::stream_wal`].
+pub type WalEntryStream = BoxStream<'static, Result<WalEntry, StorageError>>;
+
+/// Outcome of [`StorageBackend::repair_wal`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalRepairReport {
+    /// Entries left in the repaired WAL.
+    pub recovered: u64,
+    /// Lines dropped: the first unreadable or out-of-sequence line and
+    /// everything after it.
+    pub dropped: u64,
+}
+
 /// The session index containing metadata about all sessions for a tenant.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Stored indexes are read with [`SessionIndex::from_json`], which upgrades
+/// older layouts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionIndex {
+    /// Format version, see [`SESSION_INDEX_VERSION`](super::SESSION_INDEX_VERSION).
+    pub version: u32,
     pub sessions: std::collections::HashMap<String, SessionIndexEntry>,
 }
 
+impl Default for SessionIndex {
+    fn default() -> Self {
+        Self {
+            version: super::SESSION_INDEX_VERSION,
+            sessions: Default::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionIndexEntry {
     pub source_path: Option<String>,
@@ -44,6 +164,9 @@ pub struct SessionIndexEntry {
     pub modified_at: chrono::DateTime<chrono::Utc>,
     pub wal_position: u64,
     pub checkpoint_positions: Vec<u64>,
+    /// Session this one was forked from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<String>,
 }
 
 /// Storage backend abstraction for tenant-aware document storage.
@@ -81,9 +204,44 @@ pub trait StorageBackend: Send + Sync {
         session_id: &str,
     ) -> Result<bool, StorageError>;
 
+    /// Delete all of a tenant's data: sessions, WALs, checkpoints and index.
+    /// Returns whether the tenant had any data.
+    async fn delete_tenant(&self, tenant_id: &str) -> Result<bool, StorageError>;
+
     /// List all sessions for a tenant.
     async fn list_sessions(&self, tenant_id: &str) -> Result<Vec<SessionInfo>, StorageError>;
 
+    /// List a page of a tenant's sessions, ordered by session id.
+    async fn list_sessions_page(
+        &self,
+        tenant_id: &str,
+        query: &SessionListQuery,
+    ) -> Result<SessionPage, StorageError> {
+        let mut sessions: Vec<_> = self
+            .list_sessions(tenant_id)
+            .await?
+            .into_iter()
+            .filter(|s| match &query.source_path_prefix {
+                Some(prefix) => s
+                    .source_path
+                    .as_deref()
+                    .is_some_and(|path| path.starts_with(prefix.as_str())),
+                None => true,
+            })
+            .filter(|s| query.modified_after.is_none_or(|after| s.modified_at > after))
+            .collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+
+        let total = sessions.len();
+        let sessions = sessions
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(SessionPage { sessions, total })
+    }
+
     /// Check if a session exists.
     async fn session_exists(
         &self,
@@ -91,6 +249,43 @@ pub trait StorageBackend: Send + Sync {
         session_id: &str,
     ) -> Result<bool, StorageError>;
 
+    /// Check if anything is stored under a session id: the session file, a
+    /// WAL or checkpoints. Leftovers without a session file, e.g. from an
+    /// interrupted delete, count: a session created under the id would
+    /// otherwise be mixed with them.
+    ///
+    /// The default reads the WAL; backends can override it with a cheaper
+    /// existence check.
+    async fn session_id_taken(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+    ) -> Result<bool, StorageError> {
+        if self.session_exists(tenant_id, session_id).await?
+            || !self.list_checkpoints(tenant_id, session_id).await?.is_empty()
+        {
+            return Ok(true);
+        }
+        // A WAL that can't be read is still a leftover
+        let wal = self.read_wal(tenant_id, session_id, 0, Some(1)).await;
+        Ok(wal.map_or(true, |(entries, _)| !entries.is_empty()))
+    }
+
+    /// Copy a session (DOCX, WAL and checkpoints) to a new session id.
+    ///
+    /// The fork is fully independent of its source. Its index entry has no
+    /// `source_path` and records the source in `forked_from`. Fails with
+    /// `Conflict` if the new id already has a session, a WAL or checkpoints.
+    ///
+    /// Updates the tenant's index without locking it: callers must hold the
+    /// [`INDEX_LOCK_RESOURCE`](crate::lock::INDEX_LOCK_RESOURCE) lock.
+    async fn fork_session(
+        &self,
+        tenant_id: &str,
+        src_session_id: &str,
+        new_session_id: &str,
+    ) -> Result<(), StorageError>;
+
     // =========================================================================
     // Index Operations
     // =========================================================================
@@ -126,6 +321,26 @@ pub trait StorageBackend: Send + Sync {
         limit: Option<u64>,
     ) -> Result<(Vec<WalEntry>, bool), StorageError>;
 
+    /// Stream WAL entries starting from a position, at most `limit` of them.
+    ///
+    /// The default implementation buffers the result of [`read_wal`]; backends
+    /// that can read incrementally should override it so that memory use does
+    /// not grow with the WAL.
+    ///
+    /// [`read_wal`]: StorageBackend::read_wal
+    async fn stream_wal(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+        from_position: u64,
+        limit: Option<u64>,
+    ) -> Result<WalEntryStream, StorageError> {
+        let (entries, _) = self
+            .read_wal(tenant_id, session_id, from_position, limit)
+            .await?;
+        Ok(Box::pin(futures::stream::iter(entries.into_iter().map(Ok))))
+    }
+
     /// Truncate WAL, keeping only entries at or after the given position.
     async fn truncate_wal(
         &self,
@@ -134,6 +349,23 @@ pub trait StorageBackend: Send + Sync {
         keep_from: u64,
     ) -> Result<u64, StorageError>;
 
+    /// Make a corrupted WAL readable again.
+    ///
+    /// Keeps the entries before the first line that does not parse (typically
+    /// one half-written by a crash) or whose position does not follow the
+    /// previous one, and drops that line and everything after it, since later
+    /// patches depend on the lost one. Kept positions are left unchanged, so
+    /// checkpoints stay valid.
+    ///
+    /// Callers must hold the session's lock, so that no WAL append races with
+    /// the rewrite. The tenant's index is updated without locking it: callers
+    /// must also hold the [`INDEX_LOCK_RESOURCE`](crate::lock::INDEX_LOCK_RESOURCE) lock.
+    async fn repair_wal(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+    ) -> Result<WalRepairReport, StorageError>;
+
     // =========================================================================
     // Checkpoint Operations
     // =========================================================================
@@ -161,4 +393,120 @@ pub trait StorageBackend: Send + Sync {
         tenant_id: &str,
         session_id: &str,
     ) -> Result<Vec<CheckpointInfo>, StorageError>;
+
+    /// Delete checkpoints outside the retention window (see [`checkpoints_to_gc`]).
+    ///
+    /// The session's `checkpoint_positions` in the index are updated to match.
+    /// Returns the deleted positions.
+    ///
+    /// Updates the tenant's index without locking it: callers must hold the
+    /// [`INDEX_LOCK_RESOURCE`](crate::lock::INDEX_LOCK_RESOURCE) lock.
+    async fn gc_checkpoints(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+        keep_latest: usize,
+        keep_every: Option<u64>,
+    ) -> Result<Vec<u64>, StorageError>;
+
+    // =========================================================================
+    // Usage Operations
+    // =========================================================================
+
+    /// Report the bytes a tenant uses for sessions, WALs and checkpoints.
+    async fn storage_usage(&self, tenant_id: &str) -> Result<StorageUsage, StorageError>;
+
+    // =========================================================================
+    // Bundle Operations
+    // =========================================================================
+
+    /// Export a session (DOCX, WAL, checkpoints and index entry) as a single
+    /// versioned archive. See [`SessionBundle`] for the layout.
+    async fn export_bundle(
+        &self,
+        tenant_id: &str,
+        session_id: &str,
+    ) -> Result<Vec<u8>, StorageError> {
+        let session = self
+            .load_session(tenant_id, session_id)
+            .await?
+            .ok_or_else(|| StorageError::NotFound(format!("Session {} not found", session_id)))?;
+
+        let (wal, _) = self.read_wal(tenant_id, session_id, 0, None).await?;
+
+        let mut checkpoints = Vec::new();
+        for ckpt in self.list_checkpoints(tenant_id, session_id).await? {
+            if let Some((data, position)) = self
+                .load_checkpoint(tenant_id, session_id, ckpt.position)
+                .await?
+            {
+                checkpoints.push((position, data));
+            }
+        }
+
+        let index_entry = self
+            .load_index(tenant_id)
+            .await?
+            .and_then(|index| index.sessions.get(session_id).cloned());
+
+        SessionBundle {
+            session_id: session_id.to_string(),
+            index_entry,
+            session,
+            wal,
+            checkpoints,
+        }
+        .to_bytes()
+    }
+
+    /// Import a bundle produced by [`StorageBackend::export_bundle`] into a
+    /// tenant, keeping its session id. Returns that session id.
+    ///
+    /// The imported index entry drops `source_path`, which refers to a file
+    /// of the exporting deployment. Bundles without an index entry get one
+    /// built from their WAL and checkpoints. Fails with `Conflict` if the
+    /// session id is taken, see [`StorageBackend::session_id_taken`].
+    ///
+    /// Updates the tenant's index without locking it: callers must hold the
+    /// [`INDEX_LOCK_RESOURCE`](crate::lock::INDEX_LOCK_RESOURCE) lock.
+    async fn import_bundle(&self, tenant_id: &str, data: &[u8]) -> Result<String, StorageError> {
+        let bundle = SessionBundle::from_bytes(data)?;
+        let session_id = bundle.session_id;
+
+        if self.session_id_taken(tenant_id, &session_id).await? {
+            return Err(StorageError::Conflict(format!(
+                "Session {} already exists",
+                session_id
+            )));
+        }
+
+        for (position, data) in &bundle.checkpoints {
+            self.save_checkpoint(tenant_id, &session_id, *position, data)
+                .await?;
+        }
+        self.append_wal(tenant_id, &session_id, &bundle.wal).await?;
+        self.save_session(tenant_id, &session_id, &bundle.session)
+            .await?;
+
+        let now = chrono::Utc::now();
+        let entry = match bundle.index_entry {
+            Some(entry) => SessionIndexEntry {
+                source_path: None,
+                ..entry
+            },
+            None => SessionIndexEntry {
+                source_path: None,
+                created_at: now,
+                modified_at: now,
+                wal_position: bundle.wal.last().map(|e| e.position).unwrap_or(0),
+                checkpoint_positions: bundle.checkpoints.iter().map(|(p, _)| *p).collect(),
+                forked_from: None,
+            },
+        };
+        let mut index = self.load_index(tenant_id).await?.unwrap_or_default();
+        index.sessions.insert(session_id.clone(), entry);
+        self.save_index(tenant_id, &index).await?;
+
+        Ok(session_id)
+    }
 }
@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use anyhow::Context;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// Build the TLS settings of the TCP server.
+///
+/// With `client_ca`, this is mutual TLS: clients without a certificate
+/// signed by that CA are rejected during the handshake.
+pub fn server_tls_config(
+    cert: &Path,
+    key: &Path,
+    client_ca: Option<&Path>,
+) -> anyhow::Result<ServerTlsConfig> {
+    let read = |path: &Path| {
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+    };
+
+    let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(read(cert)?, read(key)?));
+    if let Some(ca) = client_ca {
+        tls = tls.client_ca_root(Certificate::from_pem(read(ca)?));
+    }
+    Ok(tls)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, ClientTlsConfig, Server};
+
+    use super::*;
+    use crate::lock::FileLock;
+    use crate::service::proto::storage_service_client::StorageServiceClient;
+    use crate::service::proto::storage_service_server::StorageServiceServer;
+    use crate::service::proto::HealthCheckRequest;
+    use crate::service::StorageServiceImpl;
+    use crate::storage::LocalStorage;
+
+    /// A CA and a certificate it signed for `localhost`, as PEM.
+    struct Pki {
+        ca_cert: String,
+        cert: String,
+        key: String,
+    }
+
+    fn pki() -> Pki {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()])
+            .unwrap()
+            .signed_by(&key, &ca, &ca_key)
+            .unwrap();
+
+        Pki {
+            ca_cert: ca.pem(),
+            cert: cert.pem(),
+            key: key.serialize_pem(),
+        }
+    }
+
+    async fn health(addr: &str, server: &Pki, client: Option<&Pki>) -> Result<(), String> {
+        let mut tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(&server.ca_cert))
+            .domain_name("localhost");
+        if let Some(client) = client {
+            tls = tls.identity(Identity::from_pem(&client.cert, &client.key));
+        }
+
+        let channel = Channel::from_shared(format!("https://{}", addr))
+            .unwrap()
+            .tls_config(tls)
+            .map_err(|e| e.to_string())?
+            .connect()
+            .await
+            .map_err(|e| e.to_string())?;
+        StorageServiceClient::new(channel)
+            .health_check(HealthCheckRequest {})
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        let temp = TempDir::new().unwrap();
+        let server_pki = pki();
+        let client_pki = pki();
+        let untrusted_pki = pki();
+
+        let write = |name: &str, content: &str| {
+            let path = temp.path().join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let tls = server_tls_config(
+            &write("server.pem", &server_pki.cert),
+            &write("server.key", &server_pki.key),
+            Some(&write("client-ca.pem", &client_pki.ca_cert)),
+        )
+        .unwrap();
+
+        let service = StorageServiceImpl::new(
+            Arc::new(LocalStorage::new(temp.path())),
+            Arc::new(FileLock::new(temp.path())),
+            64 * 1024,
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(
+            Server::builder()
+                .tls_config(tls)
+                .unwrap()
+                .add_service(StorageServiceServer::new(service))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        health(&addr, &server_pki, Some(&client_pki)).await.unwrap();
+        assert!(health(&addr, &server_pki, Some(&untrusted_pki))
+            .await
+            .is_err());
+        assert!(health(&addr, &server_pki, None).await.is_err());
+    }
+}